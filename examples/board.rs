@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::hal::delay::McycleDelay;
+use longan_nano::hal::prelude::*;
+use longan_nano::led::Led;
+use longan_nano::{sprintln, Board};
+
+#[entry]
+fn main() -> ! {
+    let board = Board::take().unwrap();
+    let mut leds = board.leds;
+    let mut delay = McycleDelay::new(&board.rcu.clocks);
+
+    sprintln!("Board is up, sysclk = {} Hz", board.rcu.clocks.sysclk().0);
+
+    let mut i = 0;
+    loop {
+        let leds: [&mut dyn Led; 3] = [&mut leds.red, &mut leds.green, &mut leds.blue];
+        leds[i].on();
+        delay.delay_ms(250);
+        leds[i].off();
+
+        i = (i + 1) % leds.len();
+    }
+}
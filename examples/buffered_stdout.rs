@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*, pac::Interrupt, eclic::*};
+use longan_nano::hal::dma::DmaExt;
+use longan_nano::stdout::{self, Drain, OverflowPolicy};
+use longan_nano::sprintln;
+
+static mut BUFFER: [u8; 512] = [0; 512];
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp.RCU.configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    // Drain stdout through DMA0 channel 3
+    let dma0 = dp.DMA0.split(&mut rcu);
+    stdout::enable_buffering(unsafe { &mut BUFFER }, Drain::Dma(dma0.4), OverflowPolicy::Block);
+
+    ECLIC::reset();
+    ECLIC::set_threshold_level(Level::L0);
+    ECLIC::set_level_priority_bits(LevelPriorityBits::L3P1);
+    ECLIC::setup(
+        Interrupt::DMA0_CHANNEL3,
+        TriggerType::Level,
+        Level::L1,
+        Priority::P1,
+    );
+    unsafe {
+        ECLIC::unmask(Interrupt::DMA0_CHANNEL3);
+        riscv::interrupt::enable();
+    };
+
+    for i in 0..10 {
+        sprintln!("Line {}: the quick brown fox jumps over the lazy dog", i);
+    }
+    stdout::flush();
+
+    loop { }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+fn DMA0_CHANNEL3() {
+    stdout::on_dma_interrupt();
+}
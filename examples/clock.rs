@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::rtc::DateTime;
+use longan_nano::{sprint, sprintln, stdout, Board};
+
+// Prints the time every second, the clock is set from a line such as
+// `2024-05-01 12:00:00` when it has lost power:
+//
+//     date '+%F %T' > /dev/ttyUSB0
+#[entry]
+fn main() -> ! {
+    let board = Board::take().unwrap();
+    let mut clock = match board.clock {
+        Some(clock) => clock,
+        None => {
+            sprintln!("The 32.768 kHz crystal did not start");
+            loop {}
+        }
+    };
+
+    let mut line = [0u8; 32];
+    while !clock.is_set() {
+        sprint!("Date and time (YYYY-MM-DD HH:MM:SS)? ");
+        let len = stdout::read_line(&mut line);
+        let text = core::str::from_utf8(&line[..len]).unwrap_or("");
+        match text.parse::<DateTime>() {
+            Ok(datetime) => clock.set(&datetime),
+            Err(_) => sprintln!("Invalid date"),
+        }
+    }
+
+    loop {
+        nb::block!(clock.rtc().wait_second()).unwrap();
+        sprintln!("{}", clock.now());
+    }
+}
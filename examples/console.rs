@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use core::fmt::Write;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use longan_nano::hal::{delay::McycleDelay, pac, prelude::*};
+use longan_nano::lcd::{self, Console};
+use longan_nano::lcd_pins;
+use riscv_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+    let mut console = Console::new(lcd);
+    let mut delay = McycleDelay::new(&rcu.clocks);
+
+    console.set_colors(Rgb565::BLACK, Rgb565::GREEN);
+    writeln!(console, " Longan Nano console ").unwrap();
+    console.set_colors(Rgb565::WHITE, Rgb565::BLACK);
+
+    let mut count = 0u32;
+    loop {
+        // Escape sequences work like on a terminal
+        writeln!(console, "\x1b[33mline {}\x1b[0m, the screen scrolls when it is full", count).unwrap();
+        count += 1;
+        delay.delay_ms(500);
+    }
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use longan_nano::hal::{delay::McycleDelay, pac, prelude::*};
+use longan_nano::lcd::ui::{Bar, Gauge, Label, StripChart, Value, Widget};
+use longan_nano::{lcd, lcd_pins};
+use riscv_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+    let mut delay = McycleDelay::new(&rcu.clocks);
+    lcd.clear(Rgb565::BLACK).unwrap();
+
+    let mut title = Label::new(Point::new(0, 0), "Signal");
+    title.set_colors(Rgb565::YELLOW, Rgb565::BLACK);
+    let mut value = Value::new(Point::new(0, 12), 1, " V");
+    let mut bar = Bar::new(Rectangle::new(Point::new(0, 26), Size::new(100, 6)), -500, 500);
+    bar.set_colors(Rgb565::GREEN, Rgb565::CSS_DIM_GRAY);
+    let mut gauge = Gauge::new(Point::new(130, 20), 40, 5, -500, 500);
+    gauge.set_colors(Rgb565::CSS_ORANGE, Rgb565::CSS_DIM_GRAY);
+    let mut chart = StripChart::new(Rectangle::new(Point::new(0, 40), Size::new(160, 40)), -500, 500);
+    chart.set_colors(Rgb565::CYAN, Rgb565::CSS_DARK_SLATE_GRAY);
+
+    // Triangle wave standing in for a measurement
+    let mut sample = 0i32;
+    let mut step = 37;
+    loop {
+        sample += step;
+        if sample.abs() >= 500 {
+            step = -step;
+        }
+
+        value.set_value(sample / 10);
+        bar.set_value(sample);
+        gauge.set_value(sample);
+        chart.push(sample);
+
+        title.draw(&mut lcd).unwrap();
+        value.draw(&mut lcd).unwrap();
+        bar.draw(&mut lcd).unwrap();
+        gauge.draw(&mut lcd).unwrap();
+        chart.draw(&mut lcd).unwrap();
+        delay.delay_ms(50);
+    }
+}
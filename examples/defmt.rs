@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::stdout;
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp.RCU.configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    defmt::info!("Hello from defmt");
+    for i in 0..10u32 {
+        defmt::debug!("i = {=u32}, i^2 = {=u32}", i, i * i);
+    }
+
+    loop { }
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::{sprint, sprintln, stdout};
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp.RCU.configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    let mut line = [0u8; 64];
+    loop {
+        sprint!("What is your name? ");
+        let len = stdout::read_line(&mut line);
+        let name = core::str::from_utf8(&line[..len]).unwrap_or("stranger");
+        sprintln!("Hello, {}!", name);
+    }
+}
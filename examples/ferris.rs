@@ -3,16 +3,17 @@
 
 use panic_halt as _;
 
-use embedded_graphics::image::{Image, ImageRaw};
-use embedded_graphics::pixelcolor::raw::LittleEndian;
+use embedded_graphics::image::Image;
 use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{Rectangle, PrimitiveStyle};
 use longan_nano::hal::{pac, prelude::*};
-use longan_nano::{lcd, lcd_pins};
+use longan_nano::lcd::{self, RleImage};
+use longan_nano::lcd_pins;
 use riscv_rt::entry;
 
-const FERRIS: &[u8] = include_bytes!("ferris.raw");
+// Converted from assets/ferris.png with the tool in rle/
+const FERRIS: &[u8] = include_bytes!("assets/ferris.rle");
 
 #[entry]
 fn main() -> ! {
@@ -41,8 +42,8 @@ fn main() -> ! {
         .unwrap();
 
     // Load Image Data
-    let raw_image: ImageRaw<Rgb565, LittleEndian> = ImageRaw::new(&FERRIS, 86);
-    Image::new(&raw_image, Point::new(width / 2 - 43, height / 2 - 32))
+    let ferris = RleImage::new(FERRIS).unwrap();
+    Image::new(&ferris, Point::new(width / 2 - 43, height / 2 - 32))
         .draw(&mut lcd)
         .unwrap();
 
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+use embedded_graphics::text::Text;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::lcd::{self, Depth, Framebuffer, PaletteIndex};
+use longan_nano::lcd_pins;
+use riscv_rt::entry;
+
+// 160x80 pixels with 4 bits each
+static mut PIXELS: [u8; 6400] = [0; 6400];
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+    let mut fb = Framebuffer::new(unsafe { &mut PIXELS }, Depth::Bpp4, lcd.size());
+
+    let text_style = MonoTextStyle::new(&FONT_6X10, PaletteIndex(15));
+    let ball_style = PrimitiveStyle::with_fill(PaletteIndex(9));
+    let background_style = PrimitiveStyle::with_fill(PaletteIndex(4));
+
+    fb.clear(PaletteIndex(4)).unwrap();
+
+    let (mut x, mut y, mut dx, mut dy) = (10, 10, 2, 1);
+    loop {
+        // Erase the ball and draw it at the new position, only the rows
+        // drawn to are sent
+        Circle::new(Point::new(x, y), 16)
+            .into_styled(background_style)
+            .draw(&mut fb)
+            .unwrap();
+
+        if x + dx < 0 || x + dx + 16 > 160 {
+            dx = -dx;
+        }
+        if y + dy < 0 || y + dy + 16 > 80 {
+            dy = -dy;
+        }
+        x += dx;
+        y += dy;
+
+        Text::new("Framebuffer", Point::new(47, 44), text_style)
+            .draw(&mut fb)
+            .unwrap();
+        Circle::new(Point::new(x, y), 16)
+            .into_styled(ball_style)
+            .draw(&mut fb)
+            .unwrap();
+        fb.flush(&mut lcd).unwrap();
+    }
+}
@@ -62,11 +62,7 @@ fn TIMER1() {
             timer1.clear_update_interrupt_flag();
         }
         if let Some(led) = R_LED.as_mut() {
-            if led.is_on() {
-                led.off();
-            } else {
-                led.on();
-            }
+            led.toggle();
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::lcd::{self, DmaLcd};
+use longan_nano::{lcd_pins, sprintln};
+use riscv_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+    longan_nano::stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+    let dma0 = dp.DMA0.split(&mut rcu);
+    let mut lcd = DmaLcd::new(lcd, dma0.3);
+
+    let screen = Rectangle::new(Point::zero(), Size::new(160, 80));
+    let colors = [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE];
+    loop {
+        for color in colors.iter() {
+            lcd.fill_solid(&screen, *color).unwrap();
+
+            // The CPU is free while the screen is filled
+            let mut spins = 0u32;
+            while lcd.is_busy() {
+                spins += 1;
+            }
+            sprintln!("{} iterations during the fill", spins);
+        }
+    }
+}
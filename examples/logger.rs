@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use log::LevelFilter;
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*, time::Hertz};
+use longan_nano::stdout::{self, Logger};
+
+static LOGGER: Logger = Logger::new(LevelFilter::Info)
+    .with_filters(&[("logger::noisy", LevelFilter::Warn)])
+    .with_timestamp(Hertz(108_000_000));
+
+mod noisy {
+    pub fn work() {
+        log::info!("this is filtered out");
+        log::warn!("this is shown");
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp.RCU.configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+    LOGGER.init().unwrap();
+
+    log::info!("Hello from the log crate");
+    log::debug!("this is filtered out");
+    noisy::work();
+
+    loop { }
+}
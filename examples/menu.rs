@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use core::cell::Cell;
+use embedded_graphics::prelude::*;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::lcd::menu::{Event, Item, Menu, SerialInput};
+use longan_nano::lcd::ui::Widget;
+use longan_nano::{lcd, lcd_pins, sprintln, stdout};
+use riscv_rt::entry;
+
+// Navigate with the arrow keys in a terminal connected to the UART
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+
+    let logging = Cell::new(true);
+    let interval = Cell::new(10);
+    let brightness = Cell::new(80);
+    let inverted = Cell::new(false);
+    let display = [
+        Item::number("Brightness", &brightness, 0, 100, 10),
+        Item::toggle("Inverted", &inverted),
+    ];
+    let items = [
+        Item::toggle("Logging", &logging),
+        Item::number("Interval (s)", &interval, 1, 60, 1),
+        Item::submenu("Display", &display),
+        Item::action("Save", 1),
+    ];
+    let mut menu = Menu::new(lcd.bounding_box(), "Settings", &items);
+    let mut keys = SerialInput::new();
+
+    loop {
+        if let Some(input) = stdout::try_read_byte().and_then(|byte| keys.feed(byte)) {
+            match menu.handle(input) {
+                Some(Event::Changed) => sprintln!(
+                    "logging {}, interval {} s, brightness {}%, inverted {}",
+                    logging.get(),
+                    interval.get(),
+                    brightness.get(),
+                    inverted.get()
+                ),
+                Some(Event::Activated(_)) => sprintln!("saved"),
+                Some(Event::Exit) => menu.reset(),
+                None => {}
+            }
+        }
+        menu.draw(&mut lcd).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+// The panic handler is provided by the `panic-lcd` feature
+
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let rcu = dp.RCU.configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let values = [1, 2, 3];
+    let index = values.len() + (rcu.clocks.sysclk().0 as usize & 1);
+    let _ = values[index];
+
+    loop { }
+}
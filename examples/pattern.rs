@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use riscv_rt::entry;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::hal::timer::Timer;
+use longan_nano::led::{rgb, Led, Pattern};
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpioc = dp.GPIOC.split(&mut rcu);
+
+    let (mut red, mut green, mut blue) = rgb(gpioc.pc13, gpioa.pa1, gpioa.pa2);
+    blue.off();
+
+    let mut red_pattern = Pattern::sos().tick_period(10);
+    let mut green_pattern = Pattern::heartbeat().tick_period(10);
+
+    let mut timer = Timer::timer2(dp.TIMER2, 100.hz(), &mut rcu);
+
+    loop {
+        if timer.wait().is_ok() {
+            red_pattern.tick(&mut red);
+            green_pattern.tick(&mut green);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use longan_nano::hal::{pac, prelude::*, pac::Interrupt, eclic::*};
+use longan_nano::hal::delay::McycleDelay;
+use longan_nano::led::RgbLed;
+use riscv_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpioc = dp.GPIOC.split(&mut rcu);
+
+    let mut led = RgbLed::new(gpioc.pc13, gpioa.pa1, gpioa.pa2, dp.TIMER1, &mut afio, &mut rcu);
+
+    ECLIC::reset();
+    ECLIC::set_threshold_level(Level::L0);
+    ECLIC::set_level_priority_bits(LevelPriorityBits::L3P1);
+    ECLIC::setup(
+        Interrupt::TIMER1,
+        TriggerType::Level,
+        Level::L1,
+        Priority::P1,
+    );
+    unsafe {
+        ECLIC::unmask(Interrupt::TIMER1);
+        riscv::interrupt::enable();
+    };
+
+    let mut delay = McycleDelay::new(&rcu.clocks);
+
+    // Walk around the colour wheel
+    let mut hue: u16 = 0;
+    loop {
+        let x = (hue % 256) as u8;
+        let (r, g, b) = match hue / 256 {
+            0 => (255, x, 0),
+            1 => (255 - x, 255, 0),
+            2 => (0, 255, x),
+            3 => (0, 255 - x, 255),
+            4 => (x, 0, 255),
+            _ => (255, 0, 255 - x),
+        };
+        led.set_color(r, g, b);
+        delay.delay_ms(5);
+
+        hue = (hue + 1) % (6 * 256);
+    }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+fn TIMER1() {
+    RgbLed::on_interrupt();
+}
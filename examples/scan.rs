@@ -5,50 +5,11 @@ extern crate panic_halt;
 
 use riscv_rt::entry;
 use longan_nano::hal::{pac, prelude::*};
+use longan_nano::mem;
 use longan_nano::{sprint, sprintln};
 
-#[inline(never)]
-pub unsafe extern "C" fn __read32(_default: usize, addr: usize) -> u32 {
-    let ptr = addr as *const u32;
-    ptr.read_volatile()
-}
-
-#[export_name = "trap_handler"]
-fn trap_handler() {
-    use riscv::register::{mcause, mepc, mtval, mcause::{Trap, Exception}};
-    let ld_insn_addr = __read32 as *const () as usize;
-
-    let mcause = mcause::read();
-    let mepc = mepc::read();
-
-    if mepc == ld_insn_addr && mcause.cause() == Trap::Exception(Exception::LoadFault) {
-        mepc::write(mepc + 2);
-        return;
-    }
-
-    sprintln!("trap!");
-    sprintln!("mcause={:08x} mepc={:08x} mtval={:08x} addr={:08x}", mcause.bits(), mepc, mtval::read(), ld_insn_addr);
-
-    loop {}
-}
-
-fn read32_safe(addr: usize) -> Option<u32> {
-    unsafe {
-        let v = __read32(0xdeadbeef, addr);
-        if v != 0xdeadbeef {
-            return Some(v);
-        }
-        let v = __read32(0x12345678, addr);
-        if v != 0x12345678 {
-            Some(v)
-        } else {
-            None
-        }
-    }
-}
-
 fn is_readable(addr: usize) -> bool {
-    read32_safe(addr).is_some()
+    mem::probe_read32(addr).is_some()
 }
 
 #[derive(Copy, Clone)]
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+use embedded_graphics::text::Text;
+use longan_nano::hal::{pac, prelude::*};
+use longan_nano::lcd::screenshot::{self, Format};
+use longan_nano::lcd::{self, Depth, Framebuffer, PaletteIndex};
+use longan_nano::{lcd_pins, stdout};
+use riscv_rt::entry;
+
+static mut PIXELS: [u8; 6400] = [0; 6400];
+
+// Save the image with e.g. `cat /dev/ttyUSB0 > screen.bmp` before reset.
+// The stock board can't read the panel back, the framebuffer is captured
+// instead.
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+    stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+
+    let mut fb = Framebuffer::new(unsafe { &mut PIXELS }, Depth::Bpp4, lcd.size());
+    Circle::new(Point::new(100, 10), 60)
+        .into_styled(PrimitiveStyle::with_fill(PaletteIndex(11)))
+        .draw(&mut fb)
+        .unwrap();
+    let style = MonoTextStyle::new(&FONT_6X10, PaletteIndex(15));
+    Text::new("Screenshot", Point::new(10, 40), style)
+        .draw(&mut fb)
+        .unwrap();
+    fb.flush(&mut lcd).unwrap();
+
+    let write = |bytes: &[u8]| {
+        stdout::write_bytes(bytes);
+        Ok::<(), ()>(())
+    };
+    match screenshot::capture(&mut lcd, Format::Bmp, write) {
+        Err(screenshot::Error::NoReadback) => fb.screenshot(Format::Bmp, write).unwrap(),
+        result => result.unwrap(),
+    }
+    stdout::flush();
+
+    loop {}
+}
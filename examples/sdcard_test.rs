@@ -3,16 +3,17 @@
 
 use embedded_sdmmc::{Directory, Volume, VolumeIdx};
 
+use longan_nano::rtc::{self, RtcTimeSource};
 use longan_nano::sdcard::SdCard;
 use panic_halt as _;
 
 use riscv_rt::entry;
-use longan_nano::hal::{pac, prelude::*};
+use longan_nano::hal::{backup_domain::BkpExt, pac, prelude::*};
 use longan_nano::{sdcard, sdcard_pins, sprint, sprintln};
 
 #[entry]
 fn main() -> ! {
-    let dp = pac::Peripherals::take().unwrap();
+    let mut dp = pac::Peripherals::take().unwrap();
 
     // Configure clocks
     let mut rcu = dp.RCU.configure()
@@ -25,9 +26,16 @@ fn main() -> ! {
     let gpioa = dp.GPIOA.split(&mut rcu);
     longan_nano::stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
 
+    // The written file gets the date of the RTC, see the clock example to set it
+    let mut backup_domain = dp.BKP.configure(&mut rcu, &mut dp.PMU);
+    match rtc::configure(dp.RTC, &mut backup_domain, &rcu) {
+        Ok(clock) => sprintln!("Clock: {}", clock.now()),
+        Err(e) => sprintln!("Clock: {:?}, files are dated 1970-01-01", e),
+    }
+
     let gpiob = dp.GPIOB.split(&mut rcu);
     let sdcard_pins = sdcard_pins!(gpiob);
-    let mut sdcard = sdcard::configure(dp.SPI1, sdcard_pins, sdcard::SdCardFreq::Safe, &mut rcu);
+    let mut sdcard = sdcard::configure_with_time_source(dp.SPI1, sdcard_pins, sdcard::SdCardFreq::Safe, RtcTimeSource {}, &mut rcu);
 
     sprint!("Initializing SD card ... ");
     if let Err(_) = sdcard.device().init() {
@@ -58,7 +66,7 @@ fn main() -> ! {
     loop { }
 }
 
-fn read_write_test(sdcard: &mut SdCard, volume: &mut Volume, dir: &Directory) {
+fn read_write_test(sdcard: &mut SdCard<RtcTimeSource>, volume: &mut Volume, dir: &Directory) {
     sprint!("Write test: ");
     let mut file = sdcard.open_file_in_dir(volume, dir, "SDTST.CSV", embedded_sdmmc::Mode::ReadWriteCreateOrTruncate).unwrap();
     let data = "1,2,3,4,20";
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_sdmmc::{DirEntry, Mode, VolumeIdx};
+use longan_nano::hal::{delay::McycleDelay, pac, prelude::*};
+use longan_nano::lcd::{self, image_file};
+use longan_nano::{lcd_pins, sdcard, sdcard_pins, sprintln};
+use riscv_rt::entry;
+
+/// Most images shown from the card
+const MAX_IMAGES: usize = 16;
+
+// Shows the BMP and QOI files of the card's root directory in turn
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure clocks
+    let mut rcu = dp
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(108.mhz())
+        .freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+    longan_nano::stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+    lcd.clear(Rgb565::BLACK).unwrap();
+
+    let sdcard_pins = sdcard_pins!(gpiob);
+    let mut sdcard = sdcard::configure(dp.SPI1, sdcard_pins, sdcard::SdCardFreq::Safe, &mut rcu);
+    sdcard.device().init().unwrap();
+    let mut volume = sdcard.get_volume(VolumeIdx(0)).unwrap();
+    let root_dir = sdcard.open_root_dir(&volume).unwrap();
+
+    let mut images: [Option<DirEntry>; MAX_IMAGES] = Default::default();
+    let mut count = 0;
+    sdcard
+        .iterate_dir(&volume, &root_dir, |entry| {
+            let extension = entry.name.extension();
+            if (extension == b"BMP" || extension == b"QOI") && count < MAX_IMAGES {
+                images[count] = Some(entry.clone());
+                count += 1;
+            }
+        })
+        .unwrap();
+    sprintln!("{} images", count);
+
+    let mut delay = McycleDelay::new(&rcu.clocks);
+    loop {
+        for entry in images[..count].iter().flatten() {
+            let mut file = sdcard.open_dir_entry(&mut volume, entry.clone(), Mode::ReadOnly).unwrap();
+            match image_file::draw(&mut lcd, &mut sdcard, &volume, &mut file, Point::zero()) {
+                Ok(size) => sprintln!("{}: {}x{}", entry.name, size.width, size.height),
+                Err(e) => sprintln!("{}: {:?}", entry.name, e),
+            }
+            sdcard.close_file(&volume, file).unwrap();
+            delay.delay_ms(3000);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Converts images into the run-length encoded format drawn by
+//! `longan_nano::lcd::RleImage`
+//!
+//! The crate can be called from the build script of an application, as a
+//! build dependency, or run on a single image from the command line:
+//!
+//! ```text
+//! cargo run --manifest-path rle/Cargo.toml -- logo.png logo.rle
+//! ```
+//!
+//! From a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::{env, path::PathBuf};
+//!
+//! fn main() {
+//!     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+//!     longan_nano_rle::convert_dir("assets", &out_dir).unwrap();
+//! }
+//! ```
+//!
+//! The application then includes the converted images:
+//!
+//! ```ignore
+//! const LOGO: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/logo.rle"));
+//! let logo = RleImage::new(LOGO).unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// Converts every PNG and BMP file of `dir` into `<name>.rle` in `out_dir`
+///
+/// Tells Cargo to run the build script again when `dir` or one of the
+/// images changes. Does nothing if `dir` does not exist.
+pub fn convert_dir(dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> io::Result<()> {
+    let dir = dir.as_ref();
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        if !is_image(&path) {
+            continue;
+        }
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let name = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| invalid("file name is not UTF-8"))?;
+        convert_file(&path, out_dir.as_ref().join(format!("{}.rle", name)))?;
+    }
+    Ok(())
+}
+
+/// Converts the PNG or BMP file `path` into `out_path`
+///
+/// The format is chosen from the file extension.
+pub fn convert_file(path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    let image = match extension.as_deref() {
+        Some("png") => read_png(path),
+        Some("bmp") => read_bmp(path),
+        _ => Err(invalid("not a PNG or BMP file")),
+    };
+
+    let (width, height, pixels) = image.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    fs::write(out_path, encode(width, height, &pixels))
+}
+
+fn is_image(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    matches!(extension.as_deref(), Some("png") | Some("bmp"))
+}
+
+/// Image size and pixels in row order
+type Rgb565Image = (u16, u16, Vec<u16>);
+
+fn rgb565(r: u8, g: u8, b: u8) -> u16 {
+    (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a PNG file, the alpha channel is ignored
+fn read_png(path: &Path) -> io::Result<Rgb565Image> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid(&e.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| invalid(&e.to_string()))?;
+
+    let channels = info.color_type.samples();
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|pixel| match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => rgb565(pixel[0], pixel[0], pixel[0]),
+            _ => rgb565(pixel[0], pixel[1], pixel[2]),
+        })
+        .collect();
+    Ok((size(info.width)?, size(info.height)?, pixels))
+}
+
+/// Reads an uncompressed 24 or 32-bit BMP file
+fn read_bmp(path: &Path) -> io::Result<Rgb565Image> {
+    let data = fs::read(path)?;
+    let u16_at = |offset: usize| data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    if data.get(..2) != Some(b"BM") {
+        return Err(invalid("not a BMP file"));
+    }
+    let header = || invalid("truncated header");
+    let pixels_offset = u32_at(10).ok_or_else(header)? as usize;
+    let width = u32_at(18).ok_or_else(header)? as i32;
+    let height = u32_at(22).ok_or_else(header)? as i32;
+    let bpp = u16_at(28).ok_or_else(header)? as usize;
+    let compression = u32_at(30).ok_or_else(header)?;
+    // Bit fields are accepted for 32-bit images in the usual BGRA layout
+    if !(bpp == 24 && compression == 0 || bpp == 32 && (compression == 0 || compression == 3)) {
+        return Err(invalid("only uncompressed 24 and 32-bit BMP files are supported"));
+    }
+
+    // A negative height means the rows are stored top-down
+    let (width, rows) = (width.unsigned_abs(), height.unsigned_abs());
+    let row_len = (width as usize * bpp / 8 + 3) & !3;
+    let mut pixels = Vec::with_capacity(width as usize * rows as usize);
+    for y in 0..rows as usize {
+        let row = if height < 0 { y } else { rows as usize - 1 - y };
+        let start = pixels_offset + row * row_len;
+        let row = data
+            .get(start..start + width as usize * bpp / 8)
+            .ok_or_else(|| invalid("truncated pixel data"))?;
+        pixels.extend(
+            row.chunks_exact(bpp / 8)
+                .map(|pixel| rgb565(pixel[2], pixel[1], pixel[0])),
+        );
+    }
+    Ok((size(width)?, size(rows)?, pixels))
+}
+
+fn size(value: u32) -> io::Result<u16> {
+    match u16::try_from(value) {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(invalid("image size out of range")),
+    }
+}
+
+/// Encodes an image of RGB565 pixels in row order, with a palette if it has
+/// at most 256 colours
+///
+/// Layout, all values little-endian:
+///
+/// - width and height, `u16` each
+/// - encoding, `u8`: 0 for RGB565 pixels, 1 for palette indices
+/// - palette length minus one, `u8`, 0 without a palette
+/// - palette, `u16` RGB565 colours
+/// - runs: a control byte `n` followed by `(n & 0x7f) + 1` pixels if bit 7
+///   is clear, or by one pixel repeated `(n & 0x7f) + 1` times if it is set
+///
+/// # Panics
+///
+/// If the image is empty or `pixels` does not hold `width * height` pixels.
+pub fn encode(width: u16, height: u16, pixels: &[u16]) -> Vec<u8> {
+    assert!(!pixels.is_empty() && pixels.len() == usize::from(width) * usize::from(height));
+
+    let mut palette = Vec::new();
+    let mut indices = HashMap::new();
+    for pixel in pixels {
+        if !indices.contains_key(pixel) {
+            indices.insert(*pixel, palette.len());
+            palette.push(*pixel);
+        }
+    }
+    let indexed = palette.len() <= 256;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    if indexed {
+        out.push(1);
+        out.push((palette.len() - 1) as u8);
+        for color in &palette {
+            out.extend_from_slice(&color.to_le_bytes());
+        }
+    } else {
+        out.extend_from_slice(&[0, 0]);
+    }
+
+    let push_pixel = |out: &mut Vec<u8>, pixel: u16| {
+        if indexed {
+            out.push(indices[&pixel] as u8);
+        } else {
+            out.extend_from_slice(&pixel.to_le_bytes());
+        }
+    };
+
+    let mut literal: Vec<u16> = Vec::new();
+    let flush_literal = |out: &mut Vec<u8>, literal: &mut Vec<u16>| {
+        for chunk in literal.chunks(128) {
+            out.push(chunk.len() as u8 - 1);
+            for pixel in chunk {
+                push_pixel(out, *pixel);
+            }
+        }
+        literal.clear();
+    };
+
+    let mut i = 0;
+    while i < pixels.len() {
+        let run = pixels[i..].iter().take(128).take_while(|p| **p == pixels[i]).count();
+        // Shorter runs are cheaper as part of a literal
+        if run >= 3 {
+            flush_literal(&mut out, &mut literal);
+            out.push(0x80 | (run as u8 - 1));
+            push_pixel(&mut out, pixels[i]);
+        } else {
+            literal.extend_from_slice(&pixels[i..i + run]);
+        }
+        i += run;
+    }
+    flush_literal(&mut out, &mut literal);
+    out
+}
@@ -0,0 +1,20 @@
+//! Converts a PNG or BMP file into the format drawn by `RleImage`
+//!
+//! ```text
+//! cargo run --manifest-path rle/Cargo.toml -- examples/assets/ferris.png examples/assets/ferris.rle
+//! ```
+
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: longan-nano-rle <image> <output>");
+        process::exit(2);
+    }
+
+    if let Err(e) = longan_nano_rle::convert_file(&args[0], &args[1]) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
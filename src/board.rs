@@ -0,0 +1,321 @@
+//! One-shot board bring-up
+//!
+//! [`Board::take`] configures the clock tree (8 MHz crystal, 108 MHz
+//! system clock), sets up stdout on the debug UART and hands out every
+//! on-board peripheral already wired up. The pins that are left unused by
+//! the board are returned in [`HeaderPins`], so using e.g. PA5 for something
+//! else while the LCD is enabled is a compile error.
+//!
+//! The RTC is started from the 32.768 kHz crystal and timestamps the files
+//! written to the SD card. The backup domain it belongs to is handed out in
+//! [`Board::backup_domain`], for the backup data registers.
+//!
+//! ```no_run
+//! use longan_nano::led::Led;
+//!
+//! let board = longan_nano::Board::take().unwrap();
+//! let mut leds = board.leds;
+//! leds.red.on();
+//! ```
+
+use gd32vf103xx_hal::afio::{Afio, AfioExt};
+use gd32vf103xx_hal::backup_domain::{BackupDomain, BkpExt};
+use gd32vf103xx_hal::gpio::gpioa::{PA0, PA11, PA12, PA13, PA14, PA15, PA3, PA4, PA8};
+use gd32vf103xx_hal::gpio::gpiob::{PB10, PB11, PB3, PB4, PB5, PB6, PB7, PB8, PB9};
+use gd32vf103xx_hal::gpio::{Debugger, Floating, GpioExt, Input};
+use gd32vf103xx_hal::pac;
+use gd32vf103xx_hal::rcu::{Rcu, RcuExt};
+use gd32vf103xx_hal::time::U32Ext;
+
+#[cfg(not(feature = "lcd"))]
+use gd32vf103xx_hal::gpio::{gpioa::{PA5, PA6, PA7}, gpiob::{PB0, PB1, PB2}};
+#[cfg(not(feature = "sdcard"))]
+use gd32vf103xx_hal::gpio::gpiob::{PB12, PB13, PB14, PB15};
+
+#[cfg(feature = "lcd")]
+use crate::lcd::{self, Lcd};
+use crate::led::{self, BLUE, GREEN, RED};
+use crate::rtc::{self, Clock};
+#[cfg(feature = "sdcard")]
+use crate::rtc::RtcTimeSource;
+#[cfg(feature = "sdcard")]
+use crate::sdcard::{self, SdCard, SdCardFreq};
+use crate::stdout;
+
+/// Baud rate used for stdout by [`Board::take`]
+pub const STDOUT_BAUD_RATE: u32 = 115_200;
+
+/// On-board RGB leds
+pub struct Leds {
+    pub red: RED,
+    pub green: GREEN,
+    pub blue: BLUE,
+}
+
+/// Header pins that are not used by the on-board peripherals
+///
+/// PC14/PC15 are not listed: they are wired to the 32.768 kHz crystal.
+/// The JTAG pins are handed out in the `Debugger` state, use
+/// [`Afio::disable_jtag`] to turn them into regular GPIOs. PA11/PA12 are
+/// the USB D-/D+ lines of the USB-C connector, they are free only as long
+/// as USB is not used.
+pub struct HeaderPins {
+    pub pa0: PA0<Input<Floating>>,
+    pub pa3: PA3<Input<Floating>>,
+    pub pa4: PA4<Input<Floating>>,
+    #[cfg(not(feature = "lcd"))]
+    pub pa5: PA5<Input<Floating>>,
+    #[cfg(not(feature = "lcd"))]
+    pub pa6: PA6<Input<Floating>>,
+    #[cfg(not(feature = "lcd"))]
+    pub pa7: PA7<Input<Floating>>,
+    pub pa8: PA8<Input<Floating>>,
+    /// USB D-
+    pub pa11: PA11<Input<Floating>>,
+    /// USB D+
+    pub pa12: PA12<Input<Floating>>,
+    pub pa13: PA13<Debugger>,
+    pub pa14: PA14<Debugger>,
+    pub pa15: PA15<Debugger>,
+    #[cfg(not(feature = "lcd"))]
+    pub pb0: PB0<Input<Floating>>,
+    #[cfg(not(feature = "lcd"))]
+    pub pb1: PB1<Input<Floating>>,
+    #[cfg(not(feature = "lcd"))]
+    pub pb2: PB2<Input<Floating>>,
+    pub pb3: PB3<Debugger>,
+    pub pb4: PB4<Debugger>,
+    pub pb5: PB5<Input<Floating>>,
+    pub pb6: PB6<Input<Floating>>,
+    pub pb7: PB7<Input<Floating>>,
+    pub pb8: PB8<Input<Floating>>,
+    pub pb9: PB9<Input<Floating>>,
+    pub pb10: PB10<Input<Floating>>,
+    pub pb11: PB11<Input<Floating>>,
+    #[cfg(not(feature = "sdcard"))]
+    pub pb12: PB12<Input<Floating>>,
+    #[cfg(not(feature = "sdcard"))]
+    pub pb13: PB13<Input<Floating>>,
+    #[cfg(not(feature = "sdcard"))]
+    pub pb14: PB14<Input<Floating>>,
+    #[cfg(not(feature = "sdcard"))]
+    pub pb15: PB15<Input<Floating>>,
+}
+
+/// Peripherals that are not used by the board
+#[allow(non_snake_case)]
+pub struct Peripherals {
+    pub ADC0: pac::ADC0,
+    pub ADC1: pac::ADC1,
+    pub CAN0: pac::CAN0,
+    pub CAN1: pac::CAN1,
+    pub CRC: pac::CRC,
+    pub DAC: pac::DAC,
+    pub DBG: pac::DBG,
+    pub DMA0: pac::DMA0,
+    pub DMA1: pac::DMA1,
+    pub EXMC: pac::EXMC,
+    pub EXTI: pac::EXTI,
+    pub FMC: pac::FMC,
+    pub FWDGT: pac::FWDGT,
+    pub GPIOD: pac::GPIOD,
+    pub GPIOE: pac::GPIOE,
+    pub I2C0: pac::I2C0,
+    pub I2C1: pac::I2C1,
+    pub ECLIC: pac::ECLIC,
+    pub PMU: pac::PMU,
+    #[cfg(not(feature = "lcd"))]
+    pub SPI0: pac::SPI0,
+    #[cfg(not(feature = "sdcard"))]
+    pub SPI1: pac::SPI1,
+    pub SPI2: pac::SPI2,
+    pub TIMER0: pac::TIMER0,
+    pub TIMER1: pac::TIMER1,
+    pub TIMER2: pac::TIMER2,
+    pub TIMER3: pac::TIMER3,
+    pub TIMER4: pac::TIMER4,
+    pub TIMER5: pac::TIMER5,
+    pub TIMER6: pac::TIMER6,
+    pub USART1: pac::USART1,
+    pub USART2: pac::USART2,
+    pub UART3: pac::UART3,
+    pub UART4: pac::UART4,
+    pub USBFS_GLOBAL: pac::USBFS_GLOBAL,
+    pub USBFS_HOST: pac::USBFS_HOST,
+    pub USBFS_DEVICE: pac::USBFS_DEVICE,
+    pub USBFS_PWRCLK: pac::USBFS_PWRCLK,
+    pub WWDGT: pac::WWDGT,
+    pub CTIMER: pac::CTIMER,
+}
+
+/// Longan Nano board with all on-board peripherals configured
+pub struct Board {
+    /// Frozen clock configuration
+    pub rcu: Rcu,
+    pub afio: Afio,
+    pub leds: Leds,
+    /// Write access to the backup domain, data register 0 is used by the
+    /// clock
+    pub backup_domain: BackupDomain,
+    /// RTC, `None` if the 32.768 kHz crystal did not start
+    pub clock: Option<Clock>,
+    #[cfg(feature = "lcd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "lcd")))]
+    pub lcd: Lcd,
+    #[cfg(feature = "sdcard")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sdcard")))]
+    pub sdcard: SdCard<RtcTimeSource>,
+    pub pins: HeaderPins,
+    pub peripherals: Peripherals,
+}
+
+impl Board {
+    /// Takes the device peripherals and brings up the board.
+    ///
+    /// Returns `None` if the peripherals have already been taken. Waits up to
+    /// 2 s for the 32.768 kHz crystal if it does not start, see
+    /// [`rtc::configure`].
+    pub fn take() -> Option<Self> {
+        pac::Peripherals::take().map(Self::new)
+    }
+
+    /// Brings up the board from the device peripherals
+    pub fn new(dp: pac::Peripherals) -> Self {
+        let mut rcu = dp
+            .RCU
+            .configure()
+            .ext_hf_clock(8.mhz())
+            .sysclk(108.mhz())
+            .freeze();
+        let mut afio = dp.AFIO.constrain(&mut rcu);
+
+        let gpioa = dp.GPIOA.split(&mut rcu);
+        let gpiob = dp.GPIOB.split(&mut rcu);
+        let gpioc = dp.GPIOC.split(&mut rcu);
+
+        stdout::configure(
+            dp.USART0, gpioa.pa9, gpioa.pa10,
+            STDOUT_BAUD_RATE.bps(), &mut afio, &mut rcu
+        );
+
+        let (red, green, blue) = led::rgb(gpioc.pc13, gpioa.pa1, gpioa.pa2);
+
+        let mut pmu = dp.PMU;
+        let mut backup_domain = dp.BKP.configure(&mut rcu, &mut pmu);
+        let clock = rtc::configure(dp.RTC, &mut backup_domain, &rcu).ok();
+
+        #[cfg(feature = "lcd")]
+        let lcd = {
+            let lcd_pins = crate::lcd_pins!(gpioa, gpiob);
+            lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu)
+        };
+
+        #[cfg(feature = "sdcard")]
+        let sdcard = {
+            let sdcard_pins = crate::sdcard_pins!(gpiob);
+            sdcard::configure_with_time_source(
+                dp.SPI1, sdcard_pins, SdCardFreq::Safe,
+                RtcTimeSource {}, &mut rcu
+            )
+        };
+
+        let pins = HeaderPins {
+            pa0: gpioa.pa0,
+            pa3: gpioa.pa3,
+            pa4: gpioa.pa4,
+            #[cfg(not(feature = "lcd"))]
+            pa5: gpioa.pa5,
+            #[cfg(not(feature = "lcd"))]
+            pa6: gpioa.pa6,
+            #[cfg(not(feature = "lcd"))]
+            pa7: gpioa.pa7,
+            pa8: gpioa.pa8,
+            pa11: gpioa.pa11,
+            pa12: gpioa.pa12,
+            pa13: gpioa.pa13,
+            pa14: gpioa.pa14,
+            pa15: gpioa.pa15,
+            #[cfg(not(feature = "lcd"))]
+            pb0: gpiob.pb0,
+            #[cfg(not(feature = "lcd"))]
+            pb1: gpiob.pb1,
+            #[cfg(not(feature = "lcd"))]
+            pb2: gpiob.pb2,
+            pb3: gpiob.pb3,
+            pb4: gpiob.pb4,
+            pb5: gpiob.pb5,
+            pb6: gpiob.pb6,
+            pb7: gpiob.pb7,
+            pb8: gpiob.pb8,
+            pb9: gpiob.pb9,
+            pb10: gpiob.pb10,
+            pb11: gpiob.pb11,
+            #[cfg(not(feature = "sdcard"))]
+            pb12: gpiob.pb12,
+            #[cfg(not(feature = "sdcard"))]
+            pb13: gpiob.pb13,
+            #[cfg(not(feature = "sdcard"))]
+            pb14: gpiob.pb14,
+            #[cfg(not(feature = "sdcard"))]
+            pb15: gpiob.pb15,
+        };
+
+        let peripherals = Peripherals {
+            ADC0: dp.ADC0,
+            ADC1: dp.ADC1,
+            CAN0: dp.CAN0,
+            CAN1: dp.CAN1,
+            CRC: dp.CRC,
+            DAC: dp.DAC,
+            DBG: dp.DBG,
+            DMA0: dp.DMA0,
+            DMA1: dp.DMA1,
+            EXMC: dp.EXMC,
+            EXTI: dp.EXTI,
+            FMC: dp.FMC,
+            FWDGT: dp.FWDGT,
+            GPIOD: dp.GPIOD,
+            GPIOE: dp.GPIOE,
+            I2C0: dp.I2C0,
+            I2C1: dp.I2C1,
+            ECLIC: dp.ECLIC,
+            PMU: pmu,
+            #[cfg(not(feature = "lcd"))]
+            SPI0: dp.SPI0,
+            #[cfg(not(feature = "sdcard"))]
+            SPI1: dp.SPI1,
+            SPI2: dp.SPI2,
+            TIMER0: dp.TIMER0,
+            TIMER1: dp.TIMER1,
+            TIMER2: dp.TIMER2,
+            TIMER3: dp.TIMER3,
+            TIMER4: dp.TIMER4,
+            TIMER5: dp.TIMER5,
+            TIMER6: dp.TIMER6,
+            USART1: dp.USART1,
+            USART2: dp.USART2,
+            UART3: dp.UART3,
+            UART4: dp.UART4,
+            USBFS_GLOBAL: dp.USBFS_GLOBAL,
+            USBFS_HOST: dp.USBFS_HOST,
+            USBFS_DEVICE: dp.USBFS_DEVICE,
+            USBFS_PWRCLK: dp.USBFS_PWRCLK,
+            WWDGT: dp.WWDGT,
+            CTIMER: dp.CTIMER,
+        };
+
+        Board {
+            rcu,
+            afio,
+            leds: Leds { red, green, blue },
+            backup_domain,
+            clock,
+            #[cfg(feature = "lcd")]
+            lcd,
+            #[cfg(feature = "sdcard")]
+            sdcard,
+            pins,
+            peripherals,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Exception handler
+//!
+//! The `fault` feature exports the `ExceptionHandler` symbol called by
+//! `riscv-rt` for every exception, so applications must not define their
+//! own. Faults raised by the probes of [`crate::mem`] are handled first.
+//! Then a hook registered with [`set_hook`] gets to look at the
+//! exception and can resume execution. Otherwise the exception is reported
+//! through [`crate::stdout`], on the LCD with the `panic-lcd` feature, and the
+//! red LED blinks SOS forever.
+//!
+//! ```
+//! fn skip_faulting_loads(fault: &Fault, _: &mut TrapFrame) -> Recovery {
+//!     match fault.exception() {
+//!         Exception::LoadFault => Recovery::Resume(fault.next_instruction()),
+//!         _ => Recovery::Unhandled,
+//!     }
+//! }
+//!
+//! fault::set_hook(skip_faulting_loads);
+//! ```
+
+use core::fmt;
+use riscv::interrupt;
+use riscv::register::{mcause, mepc, mtval};
+
+pub use riscv::register::mcause::Exception;
+
+use crate::{sprint, sprintln};
+
+/// Number of stack words printed in a report
+const STACK_DUMP_WORDS: usize = 16;
+
+/// Registers saved by `riscv-rt` on trap entry
+///
+/// Changes are written back to the registers when the handler returns.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TrapFrame {
+    pub ra: usize,
+    pub t0: usize,
+    pub t1: usize,
+    pub t2: usize,
+    pub t3: usize,
+    pub t4: usize,
+    pub t5: usize,
+    pub t6: usize,
+    pub a0: usize,
+    pub a1: usize,
+    pub a2: usize,
+    pub a3: usize,
+    pub a4: usize,
+    pub a5: usize,
+    pub a6: usize,
+    pub a7: usize,
+}
+
+impl TrapFrame {
+    /// Stack pointer at the time of the exception
+    pub fn sp(&self) -> usize {
+        self as *const Self as usize + core::mem::size_of::<Self>()
+    }
+}
+
+/// Exception being handled
+#[derive(Clone, Copy, Debug)]
+pub struct Fault {
+    pub mcause: usize,
+    pub mepc: usize,
+    pub mtval: usize,
+}
+
+impl Fault {
+    /// Reads the trap registers
+    pub fn read() -> Self {
+        Fault {
+            mcause: mcause::read().bits(),
+            mepc: mepc::read(),
+            mtval: mtval::read(),
+        }
+    }
+
+    /// Decoded exception code
+    ///
+    /// In ECLIC mode, the upper bits of mcause hold the previous interrupt
+    /// state, only the low 12 bits are the exception code.
+    pub fn exception(&self) -> Exception {
+        Exception::from(self.mcause & 0xfff)
+    }
+
+    /// Human readable name of the exception
+    pub fn name(&self) -> &'static str {
+        match self.exception() {
+            Exception::InstructionMisaligned => "Instruction address misaligned",
+            Exception::InstructionFault => "Instruction access fault",
+            Exception::IllegalInstruction => "Illegal instruction",
+            Exception::Breakpoint => "Breakpoint",
+            Exception::LoadMisaligned => "Load address misaligned",
+            Exception::LoadFault => "Load access fault",
+            Exception::StoreMisaligned => "Store address misaligned",
+            Exception::StoreFault => "Store access fault",
+            Exception::UserEnvCall => "Environment call from U-mode",
+            Exception::SupervisorEnvCall => "Environment call from S-mode",
+            Exception::MachineEnvCall => "Environment call from M-mode",
+            Exception::InstructionPageFault => "Instruction page fault",
+            Exception::LoadPageFault => "Load page fault",
+            Exception::StorePageFault => "Store page fault",
+            Exception::Unknown if self.mcause & 0xfff == 0xfff => "NMI",
+            Exception::Unknown => "Unknown exception",
+        }
+    }
+
+    /// Address of the instruction following the faulting one
+    ///
+    /// The faulting instruction is read to find out whether it is
+    /// compressed, so this must not be used for instruction faults.
+    pub fn next_instruction(&self) -> usize {
+        let low_half = unsafe { (self.mepc as *const u16).read_volatile() };
+        if low_half & 0b11 == 0b11 {
+            self.mepc + 4
+        } else {
+            self.mepc + 2
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (mcause={:08x} mepc={:08x} mtval={:08x})",
+            self.name(),
+            self.mcause,
+            self.mepc,
+            self.mtval
+        )
+    }
+}
+
+/// Outcome of a hook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// Report the exception and halt
+    Unhandled,
+    /// Continue execution at the given address
+    Resume(usize),
+}
+
+/// Function looking at exceptions before they are reported
+pub type Hook = fn(&Fault, &mut TrapFrame) -> Recovery;
+
+static mut HOOK: Option<Hook> = None;
+
+/// Registers the hook called for every exception, replacing the previous one
+pub fn set_hook(hook: Hook) {
+    interrupt::free(|| unsafe {
+        HOOK = Some(hook);
+    })
+}
+
+/// Removes the hook
+pub fn clear_hook() {
+    interrupt::free(|| unsafe {
+        HOOK = None;
+    })
+}
+
+// `riscv-rt` passes the frame as `&TrapFrame`, but it lives on the trap
+// stack and is reloaded into the registers on return, so it is taken as a
+// pointer and only written through that pointer.
+#[export_name = "ExceptionHandler"]
+extern "C" fn exception_handler(trap_frame: *mut TrapFrame) {
+    let fault = Fault::read();
+
+    if let Some(address) = unsafe { crate::mem::recover(&fault, trap_frame) } {
+        mepc::write(address);
+        return;
+    }
+
+    if let Some(hook) = unsafe { HOOK } {
+        if let Recovery::Resume(address) = hook(&fault, unsafe { &mut *trap_frame }) {
+            mepc::write(address);
+            return;
+        }
+    }
+
+    report(&fault, unsafe { &*trap_frame });
+
+    #[cfg(feature = "panic-lcd")]
+    unsafe {
+        let trap = crate::lcd::fault_screen::TrapInfo {
+            mcause: fault.mcause,
+            mepc: fault.mepc,
+            mtval: fault.mtval,
+        };
+        crate::lcd::fault_screen::show(fault.name(), format_args!("at {:08x}", fault.mepc), Some(&trap));
+    }
+
+    crate::panic::signal_forever()
+}
+
+/// Prints the exception, the saved registers and the top of the stack
+fn report(fault: &Fault, frame: &TrapFrame) {
+    extern "C" {
+        static _stack_start: u32;
+    }
+
+    crate::panic::configure_stdout();
+
+    sprintln!("\n*** {} ***", fault.name());
+    sprintln!("mcause {:08x}  mepc {:08x}  mtval {:08x}", fault.mcause, fault.mepc, fault.mtval);
+
+    let registers = [
+        ("ra", frame.ra),
+        ("sp", frame.sp()),
+        ("t0", frame.t0),
+        ("t1", frame.t1),
+        ("t2", frame.t2),
+        ("t3", frame.t3),
+        ("t4", frame.t4),
+        ("t5", frame.t5),
+        ("t6", frame.t6),
+        ("a0", frame.a0),
+        ("a1", frame.a1),
+        ("a2", frame.a2),
+        ("a3", frame.a3),
+        ("a4", frame.a4),
+        ("a5", frame.a5),
+        ("a6", frame.a6),
+        ("a7", frame.a7),
+    ];
+    for line in registers.chunks(4) {
+        for (name, value) in line {
+            sprint!("{:<3}{:08x}  ", name, value);
+        }
+        sprintln!();
+    }
+
+    let stack_top = unsafe { &_stack_start as *const u32 as usize };
+    let mut address = frame.sp();
+    sprintln!("stack:");
+    for _ in 0..STACK_DUMP_WORDS / 4 {
+        if address >= stack_top {
+            break;
+        }
+        sprint!("{:08x}:", address);
+        for _ in 0..4 {
+            if address >= stack_top {
+                break;
+            }
+            let word = unsafe { (address as *const u32).read_volatile() };
+            sprint!(" {:08x}", word);
+            address += 4;
+        }
+        sprintln!();
+    }
+    crate::stdout::flush();
+}
@@ -9,8 +9,32 @@ use gd32vf103xx_hal::gpio::{Alternate, Floating, Input, Output, PushPull};
 use gd32vf103xx_hal::pac::SPI0;
 use gd32vf103xx_hal::rcu::Rcu;
 use gd32vf103xx_hal::spi::{Spi, MODE_0};
-use gd32vf103xx_hal::time::U32Ext;
-use st7735_lcd::{Orientation, ST7735};
+use gd32vf103xx_hal::time::Hertz;
+use st7735_lcd::ST7735;
+
+pub use st7735_lcd::Orientation;
+
+mod command;
+mod console;
+pub mod dma;
+pub mod fault_screen;
+mod framebuffer;
+pub mod image;
+#[cfg(feature = "sdcard")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sdcard")))]
+pub mod image_file;
+pub mod menu;
+mod power;
+pub mod screenshot;
+mod scroll;
+pub mod ui;
+
+pub use console::Console;
+pub use dma::DmaLcd;
+pub use framebuffer::{Depth, Framebuffer, PaletteIndex};
+pub use image::RleImage;
+pub use power::Power;
+pub use scroll::{Scroll, ScrollArea};
 
 /// Sets up all the needed GPIO pins for the LCD
 ///
@@ -52,15 +76,110 @@ pub struct LcdPins {
     pub rst: RstPin,
 }
 
+/// Settings of the LCD
+///
+/// The defaults match the panel of the Longan Nano: landscape, 16 MHz SPI
+/// clock, inverted colours and BGR order.
+///
+/// ```
+/// let config = LcdConfig::new()
+///     .with_orientation(Orientation::PortraitSwapped)
+///     .with_spi_frequency(8.mhz().into());
+/// let mut lcd = lcd::configure_with(dp.SPI0, lcd_pins, config, &mut afio, &mut rcu);
+/// ```
+pub struct LcdConfig {
+    orientation: Orientation,
+    spi_frequency: Hertz,
+    inverted: bool,
+    bgr: bool,
+    offset: Option<(u16, u16)>,
+}
+
+impl LcdConfig {
+    /// Creates the default settings
+    pub const fn new() -> Self {
+        LcdConfig {
+            orientation: Orientation::Landscape,
+            spi_frequency: Hertz(16_000_000),
+            inverted: true,
+            bgr: true,
+            offset: None,
+        }
+    }
+
+    /// Sets the orientation, the screen is 80x160 pixels in the portrait
+    /// orientations and 160x80 in the landscape ones
+    pub const fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Sets the SPI clock, at most half the APB2 clock
+    pub const fn with_spi_frequency(mut self, frequency: Hertz) -> Self {
+        self.spi_frequency = frequency;
+        self
+    }
+
+    /// Sets whether colours are inverted, which depends on the panel revision
+    pub const fn with_inversion(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Sets whether the panel expects blue-green-red instead of
+    /// red-green-blue order
+    pub const fn with_bgr(mut self, bgr: bool) -> Self {
+        self.bgr = bgr;
+        self
+    }
+
+    /// Overrides the position of the visible area in the controller memory
+    ///
+    /// By default it is computed from the orientation: the 80x160 panel is
+    /// centred in the 132x162 memory of the ST7735S.
+    pub const fn with_offset(mut self, x: u16, y: u16) -> Self {
+        self.offset = Some((x, y));
+        self
+    }
+
+    /// Returns the width and height of the screen
+    pub fn size(&self) -> (u32, u32) {
+        match self.orientation {
+            Orientation::Portrait | Orientation::PortraitSwapped => (80, 160),
+            Orientation::Landscape | Orientation::LandscapeSwapped => (160, 80),
+        }
+    }
+
+    /// Returns the position of the visible area in the controller memory
+    pub fn offset(&self) -> (u16, u16) {
+        // Both margins are the same on each axis, so mirroring does not
+        // change the offset
+        let (width, height) = self.size();
+        self.offset.unwrap_or(if width < height { (26, 1) } else { (1, 26) })
+    }
+}
+
+impl Default for LcdConfig {
+    fn default() -> Self {
+        LcdConfig::new()
+    }
+}
+
 /// Constructs LCD driver from the required components
 pub fn configure(spi: SPI0, pins: LcdPins, afio: &mut Afio, rcu: &mut Rcu) -> Lcd {
-    let (width, height) = (160, 80);
+    configure_with(spi, pins, LcdConfig::new(), afio, rcu)
+}
+
+/// Constructs LCD driver from the required components and settings
+pub fn configure_with(spi: SPI0, pins: LcdPins, config: LcdConfig, afio: &mut Afio, rcu: &mut Rcu) -> Lcd {
+    let (width, height) = config.size();
+    let (dx, dy) = config.offset();
     let spi0 = Spi::spi0(
         spi,
         (pins.sck, pins.miso, pins.mosi),
         afio,
         MODE_0,
-        16.mhz(),
+        config.spi_frequency,
         rcu,
     );
 
@@ -71,14 +190,14 @@ pub fn configure(spi: SPI0, pins: LcdPins, afio: &mut Afio, rcu: &mut Rcu) -> Lc
         spi0, 
         pins.dc, 
         pins.rst, 
-        false, 
-        true, 
+        !config.bgr, 
+        config.inverted, 
         width, 
         height);
     let mut delay = McycleDelay::new(&rcu.clocks);
     lcd.init(&mut delay).unwrap();
-    lcd.set_orientation(&Orientation::Landscape).unwrap();
-    lcd.set_offset(1, 26);
+    lcd.set_orientation(&config.orientation).unwrap();
+    lcd.set_offset(dx, dy);
 
     lcd
-    }
+}
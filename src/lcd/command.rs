@@ -0,0 +1,46 @@
+//! Raw ST7735 commands
+//!
+//! The `st7735-lcd` driver only exposes drawing, so other commands are sent
+//! by driving SPI0 and the D/C pin directly. Each byte is also read back,
+//! leaving SPI0 in the state the driver expects.
+
+use gd32vf103xx_hal::pac::{GPIOB, SPI0};
+
+use super::Lcd;
+
+pub(super) const NOP: u8 = 0x00;
+pub(super) const RDDID: u8 = 0x04;
+pub(super) const SLPIN: u8 = 0x10;
+pub(super) const SLPOUT: u8 = 0x11;
+pub(super) const NORON: u8 = 0x13;
+pub(super) const DISPOFF: u8 = 0x28;
+pub(super) const DISPON: u8 = 0x29;
+pub(super) const RAMRD: u8 = 0x2e;
+pub(super) const VSCRDEF: u8 = 0x33;
+pub(super) const VSCSAD: u8 = 0x37;
+pub(super) const IDMOFF: u8 = 0x38;
+pub(super) const IDMON: u8 = 0x39;
+
+/// Sends `command` followed by its parameters
+///
+/// Taking the LCD makes sure nobody else is using SPI0 meanwhile.
+pub(super) fn write(_lcd: &mut Lcd, command: u8, params: &[u8]) {
+    let gpiob = unsafe { &*GPIOB::ptr() };
+
+    // D/C low for the command byte, high for parameters
+    gpiob.bc.write(|w| w.cr0().set_bit());
+    transfer(command);
+    gpiob.bop.write(|w| w.bop0().set_bit());
+    for param in params {
+        transfer(*param);
+    }
+}
+
+/// Sends a byte and returns the byte received meanwhile
+pub(super) fn transfer(byte: u8) -> u8 {
+    let spi = unsafe { &*SPI0::ptr() };
+    while spi.stat.read().tbe().bit_is_clear() {}
+    spi.data.write(|w| unsafe { w.bits(byte.into()) });
+    while spi.stat.read().rbne().bit_is_clear() {}
+    spi.data.read().bits() as u8
+}
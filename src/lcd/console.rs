@@ -0,0 +1,342 @@
+//! Text console
+
+use core::fmt;
+use embedded_graphics::mono_font::{ascii::FONT_5X8, MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use super::Lcd;
+use ansi::{Action, Parser, Sequence, PALETTE};
+
+pub(super) mod ansi;
+
+const CHAR_WIDTH: usize = 5;
+const CHAR_HEIGHT: usize = 8;
+const TAB_WIDTH: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    ch: u8,
+    foreground: Rgb565,
+    background: Rgb565,
+}
+
+/// Terminal-like text output on the LCD
+///
+/// Text is drawn with a 5x8 font on a grid of 32 columns and 10 rows. Lines
+/// longer than the screen wrap, and the screen scrolls up when the cursor
+/// moves past the last row. `\n`, `\r`, `\t` and backspace are interpreted,
+/// other characters outside printable ASCII are shown as `?`. The LCD has
+/// to be in a landscape orientation.
+///
+/// Scrolling redraws the cells that change. [`Scroll`](super::Scroll) cannot
+/// be used for it: the controller only scrolls along the long side of the
+/// panel, which is horizontal in the landscape orientations.
+///
+/// A subset of the VT100/ANSI escape sequences is supported, so that the
+/// same output can be sent to a terminal over UART:
+///
+/// - `ESC [ n m`: colours and attributes. 0 (reset), 1 and 22 (bold, shown
+///   as bright colours), 7 and 27 (reverse), 30-37, 39, 40-47, 49, 90-97,
+///   100-107, and `38;5;n`, `48;5;n`, `38;2;r;g;b`, `48;2;r;g;b`
+/// - `ESC [ row ; column H` and `f`: cursor position
+/// - `ESC [ n A`, `B`, `C`, `D`, `G`: cursor movement
+/// - `ESC [ n J` and `ESC [ n K`: clear screen and line
+/// - `ESC [ s` and `ESC [ u`: save and restore the cursor position
+///
+/// Other sequences are ignored.
+///
+/// ```
+/// let mut console = Console::new(lcd);
+/// console.set_colors(Rgb565::GREEN, Rgb565::BLACK);
+/// writeln!(console, "uptime: {} s", seconds).unwrap();
+/// write!(console, "\x1b[31merror\x1b[0m").unwrap();
+/// ```
+pub struct Console {
+    lcd: Lcd,
+    cells: [[Cell; Console::COLUMNS]; Console::ROWS],
+    column: usize,
+    row: usize,
+    saved_cursor: (usize, usize),
+    foreground: Rgb565,
+    background: Rgb565,
+    default_foreground: Rgb565,
+    default_background: Rgb565,
+    bold: bool,
+    reverse: bool,
+    parser: Parser,
+}
+
+impl Console {
+    /// Number of characters per line
+    pub const COLUMNS: usize = 160 / CHAR_WIDTH;
+    /// Number of lines
+    pub const ROWS: usize = 80 / CHAR_HEIGHT;
+
+    /// Creates a console with white text on black and clears the screen
+    pub fn new(lcd: Lcd) -> Self {
+        let blank = Cell {
+            ch: b' ',
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+        };
+        let mut console = Console {
+            lcd,
+            cells: [[blank; Console::COLUMNS]; Console::ROWS],
+            column: 0,
+            row: 0,
+            saved_cursor: (0, 0),
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+            default_foreground: Rgb565::WHITE,
+            default_background: Rgb565::BLACK,
+            bold: false,
+            reverse: false,
+            parser: Parser::new(),
+        };
+        let _ = console.lcd.clear(Rgb565::BLACK);
+        console
+    }
+
+    /// Releases the LCD
+    pub fn free(self) -> Lcd {
+        self.lcd
+    }
+
+    /// Sets the colours of the text written from now on
+    ///
+    /// They are also the colours restored by `ESC [ 0 m`.
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.foreground = foreground;
+        self.background = background;
+        self.default_foreground = foreground;
+        self.default_background = background;
+    }
+
+    /// Returns the foreground and background colours
+    pub fn colors(&self) -> (Rgb565, Rgb565) {
+        (self.foreground, self.background)
+    }
+
+    /// Moves the cursor, positions past the screen are clamped
+    pub fn set_cursor(&mut self, column: usize, row: usize) {
+        self.column = column.min(Console::COLUMNS - 1);
+        self.row = row.min(Console::ROWS - 1);
+    }
+
+    /// Returns the column and row of the cursor
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column.min(Console::COLUMNS - 1), self.row)
+    }
+
+    /// Fills the screen with the background colour and moves the cursor
+    /// home
+    pub fn clear(&mut self) -> Result<(), ()> {
+        let blank = self.blank();
+        self.cells = [[blank; Console::COLUMNS]; Console::ROWS];
+        self.column = 0;
+        self.row = 0;
+        self.lcd.clear(self.background)
+    }
+
+    /// Writes a single character, which can be part of an escape sequence
+    pub fn put_char(&mut self, c: char) -> Result<(), ()> {
+        match self.parser.advance(c) {
+            Some(Action::Print(c)) => self.print(c),
+            Some(Action::Execute(sequence)) => self.execute(&sequence),
+            None => Ok(()),
+        }
+    }
+
+    fn print(&mut self, c: char) -> Result<(), ()> {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => {
+                self.column = 0;
+                Ok(())
+            }
+            '\t' => {
+                let spaces = TAB_WIDTH - self.column % TAB_WIDTH;
+                for _ in 0..spaces {
+                    self.print(' ')?;
+                }
+                Ok(())
+            }
+            '\x08' => {
+                self.column = self.column.min(Console::COLUMNS - 1).saturating_sub(1);
+                Ok(())
+            }
+            _ => {
+                // Wrapping is deferred so that a full line does not leave an
+                // empty one below it
+                if self.column == Console::COLUMNS {
+                    self.new_line()?;
+                }
+                let ch = if c.is_ascii() && !c.is_ascii_control() { c as u8 } else { b'?' };
+                let mut cell = Cell {
+                    ch,
+                    foreground: self.foreground,
+                    background: self.background,
+                };
+                if self.reverse {
+                    core::mem::swap(&mut cell.foreground, &mut cell.background);
+                }
+                self.cells[self.row][self.column] = cell;
+                self.draw_cell(self.column, self.row)?;
+                self.column += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn execute(&mut self, sequence: &Sequence) -> Result<(), ()> {
+        let (column, row) = self.cursor();
+        let n = usize::from(sequence.param_or(0, 1));
+        match sequence.command {
+            'A' => self.set_cursor(column, row.saturating_sub(n)),
+            'B' => self.set_cursor(column, row + n),
+            'C' => self.set_cursor(column + n, row),
+            'D' => self.set_cursor(column.saturating_sub(n), row),
+            'G' => self.set_cursor(n - 1, row),
+            'H' | 'f' => {
+                let column = usize::from(sequence.param_or(1, 1));
+                self.set_cursor(column - 1, n - 1)
+            }
+            'J' => {
+                let cursor = row * Console::COLUMNS + column;
+                return match sequence.param_or(0, 0) {
+                    0 => self.erase(cursor, Console::ROWS * Console::COLUMNS),
+                    1 => self.erase(0, cursor + 1),
+                    _ => self.erase(0, Console::ROWS * Console::COLUMNS),
+                };
+            }
+            'K' => {
+                let start = row * Console::COLUMNS;
+                return match sequence.param_or(0, 0) {
+                    0 => self.erase(start + column, start + Console::COLUMNS),
+                    1 => self.erase(start, start + column + 1),
+                    _ => self.erase(start, start + Console::COLUMNS),
+                };
+            }
+            'm' => self.select_graphic_rendition(sequence.params()),
+            's' => self.saved_cursor = (column, row),
+            'u' => self.set_cursor(self.saved_cursor.0, self.saved_cursor.1),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        let mut params = if params.is_empty() { &[0][..] } else { params };
+        while let Some((&code, rest)) = params.split_first() {
+            params = rest;
+            match code {
+                0 => {
+                    self.foreground = self.default_foreground;
+                    self.background = self.default_background;
+                    self.bold = false;
+                    self.reverse = false;
+                }
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                30..=37 => {
+                    let bright = if self.bold { 8 } else { 0 };
+                    self.foreground = PALETTE[usize::from(code - 30) + bright];
+                }
+                39 => self.foreground = self.default_foreground,
+                40..=47 => self.background = PALETTE[usize::from(code - 40)],
+                49 => self.background = self.default_background,
+                90..=97 => self.foreground = PALETTE[usize::from(code - 90) + 8],
+                100..=107 => self.background = PALETTE[usize::from(code - 100) + 8],
+                38 | 48 => {
+                    let (color, used) = ansi::extended_color(params);
+                    params = &params[used..];
+                    match color {
+                        Some(color) if code == 38 => self.foreground = color,
+                        Some(color) => self.background = color,
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Blanks the cells from index `start` up to `end`, counted row by row
+    fn erase(&mut self, start: usize, end: usize) -> Result<(), ()> {
+        let blank = self.blank();
+        for index in start..end.min(Console::ROWS * Console::COLUMNS) {
+            let (row, column) = (index / Console::COLUMNS, index % Console::COLUMNS);
+            if self.cells[row][column] != blank {
+                self.cells[row][column] = blank;
+                self.draw_cell(column, row)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: b' ',
+            foreground: self.foreground,
+            background: self.background,
+        }
+    }
+
+    fn new_line(&mut self) -> Result<(), ()> {
+        self.column = 0;
+        if self.row + 1 < Console::ROWS {
+            self.row += 1;
+            Ok(())
+        } else {
+            self.scroll_up()
+        }
+    }
+
+    /// Moves all lines up by one and blanks the last one
+    fn scroll_up(&mut self) -> Result<(), ()> {
+        let blank = self.blank();
+        for row in 0..Console::ROWS {
+            for column in 0..Console::COLUMNS {
+                let cell = if row + 1 < Console::ROWS {
+                    self.cells[row + 1][column]
+                } else {
+                    blank
+                };
+                // Only redraw the cells that change
+                if cell != self.cells[row][column] {
+                    self.cells[row][column] = cell;
+                    self.draw_cell(column, row)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_cell(&mut self, column: usize, row: usize) -> Result<(), ()> {
+        let cell = self.cells[row][column];
+        let style: MonoTextStyle<Rgb565> = MonoTextStyleBuilder::new()
+            .font(&FONT_5X8)
+            .text_color(cell.foreground)
+            .background_color(cell.background)
+            .build();
+        let position = Point::new((column * CHAR_WIDTH) as i32, (row * CHAR_HEIGHT) as i32);
+        let mut buf = [0; 4];
+        let text = (cell.ch as char).encode_utf8(&mut buf);
+        Text::with_baseline(text, position, style, Baseline::Top)
+            .draw(&mut self.lcd)
+            .map(|_| ())
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.put_char(c).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! Escape sequence parser
+
+use embedded_graphics::pixelcolor::Rgb565;
+
+const MAX_PARAMS: usize = 8;
+
+/// Control sequence with its parameters
+pub(super) struct Sequence {
+    params: [u16; MAX_PARAMS],
+    count: usize,
+    pub(super) command: char,
+}
+
+impl Sequence {
+    /// Returns the parameters, missing ones are 0
+    pub(super) fn params(&self) -> &[u16] {
+        &self.params[..self.count.min(MAX_PARAMS)]
+    }
+
+    /// Returns parameter `index`, with missing and 0 values replaced by
+    /// `default`
+    pub(super) fn param_or(&self, index: usize, default: u16) -> u16 {
+        match self.params().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+pub(super) enum Action {
+    Print(char),
+    Execute(Sequence),
+}
+
+enum State {
+    Ground,
+    Escape,
+    Csi { private: bool },
+}
+
+/// Splits a character stream into text and `ESC [` control sequences
+///
+/// Other escape sequences and private control sequences, such as
+/// `ESC [ ? 25 l`, are dropped.
+pub(super) struct Parser {
+    state: State,
+    params: [u16; MAX_PARAMS],
+    count: usize,
+}
+
+impl Parser {
+    pub(super) const fn new() -> Self {
+        Parser {
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            count: 0,
+        }
+    }
+
+    pub(super) fn advance(&mut self, c: char) -> Option<Action> {
+        match self.state {
+            State::Ground => {
+                if c == '\x1b' {
+                    self.state = State::Escape;
+                    None
+                } else {
+                    Some(Action::Print(c))
+                }
+            }
+            State::Escape => {
+                if c == '[' {
+                    self.params = [0; MAX_PARAMS];
+                    self.count = 0;
+                    self.state = State::Csi { private: false };
+                } else {
+                    self.state = State::Ground;
+                }
+                None
+            }
+            State::Csi { private } => match c {
+                '0'..='9' => {
+                    if self.count == 0 {
+                        self.count = 1;
+                    }
+                    if let Some(param) = self.params.get_mut(self.count - 1) {
+                        *param = param.saturating_mul(10).saturating_add(c as u16 - '0' as u16);
+                    }
+                    None
+                }
+                ';' => {
+                    // Extra parameters are dropped
+                    self.count = (self.count.max(1) + 1).min(MAX_PARAMS + 1);
+                    None
+                }
+                '\x20'..='\x3f' => {
+                    self.state = State::Csi { private: true };
+                    None
+                }
+                '\x40'..='\x7e' => {
+                    self.state = State::Ground;
+                    if private {
+                        return None;
+                    }
+                    Some(Action::Execute(Sequence {
+                        params: self.params,
+                        count: self.count,
+                        command: c,
+                    }))
+                }
+                _ => {
+                    self.state = State::Ground;
+                    None
+                }
+            },
+        }
+    }
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Rgb565 {
+    Rgb565::new(r >> 3, g >> 2, b >> 3)
+}
+
+/// The 16 standard colours, normal then bright
+pub(super) const PALETTE: [Rgb565; 16] = [
+    rgb(0, 0, 0),
+    rgb(170, 0, 0),
+    rgb(0, 170, 0),
+    rgb(170, 85, 0),
+    rgb(0, 0, 170),
+    rgb(170, 0, 170),
+    rgb(0, 170, 170),
+    rgb(170, 170, 170),
+    rgb(85, 85, 85),
+    rgb(255, 85, 85),
+    rgb(85, 255, 85),
+    rgb(255, 255, 85),
+    rgb(85, 85, 255),
+    rgb(255, 85, 255),
+    rgb(85, 255, 255),
+    rgb(255, 255, 255),
+];
+
+/// Converts an entry of the 256 colour palette
+pub(in crate::lcd) fn color_256(index: u16) -> Rgb565 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => PALETTE[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            rgb(
+                LEVELS[(i / 36) as usize],
+                LEVELS[(i / 6 % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = (8 + 10 * (index.min(255) - 232)) as u8;
+            rgb(level, level, level)
+        }
+    }
+}
+
+/// Parses the colour following SGR 38 or 48, `5;n` or `2;r;g;b`
+///
+/// Returns the colour and the number of parameters used.
+pub(super) fn extended_color(params: &[u16]) -> (Option<Rgb565>, usize) {
+    match params {
+        [5, index, ..] => (Some(color_256(*index)), 2),
+        [2, r, g, b, ..] => {
+            let channel = |value: u16| value.min(255) as u8;
+            (Some(rgb(channel(*r), channel(*g), channel(*b))), 4)
+        }
+        _ => (None, params.len()),
+    }
+}
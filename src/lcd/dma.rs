@@ -0,0 +1,206 @@
+//! DMA transfers to the LCD
+//!
+//! Pixel data is sent by DMA0 channel 2 while the CPU carries on. SPI0 is
+//! switched to 16-bit frames for the duration of a transfer, so RGB565
+//! words go out in the byte order the LCD expects.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_graphics::pixelcolor::{IntoStorage, Rgb565};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use gd32vf103xx_hal::dma::{dma0::C2, Direction, Event};
+use gd32vf103xx_hal::pac::{DMA0, SPI0};
+use riscv::interrupt;
+
+use super::Lcd;
+
+/// Set while a transfer is running
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+static mut CALLBACK: Option<fn()> = None;
+
+/// Source of fills, DMA reads it without incrementing the address
+static mut FILL_COLOR: u16 = 0;
+
+/// LCD whose pixel writes are done by DMA
+///
+/// Each operation first waits for the previous transfer to finish. Poll
+/// [`is_busy`](DmaLcd::is_busy) or register a callback with
+/// [`listen`](DmaLcd::listen) to know when a transfer is done.
+///
+/// ```
+/// let dma0 = dp.DMA0.split(&mut rcu);
+/// let mut lcd = DmaLcd::new(lcd, dma0.3);
+/// let screen = Rectangle::new(Point::zero(), Size::new(160, 80));
+/// lcd.fill_solid(&screen, Rgb565::BLACK).unwrap();
+/// // Prepare the next frame while the screen is cleared
+/// ```
+pub struct DmaLcd {
+    lcd: Lcd,
+    dma: C2,
+}
+
+impl DmaLcd {
+    /// Sets up DMA0 channel 2 for SPI0 transmission
+    pub fn new(lcd: Lcd, mut dma: C2) -> Self {
+        let spi = unsafe { &*SPI0::ptr() };
+        unsafe {
+            dma.set_peripheral_address(&spi.data as *const _ as u32, false);
+        }
+        dma.set_direction(Direction::MemoryToPeripherial);
+        // 16-bit transfers on both sides
+        dma.ctl().modify(|_, w| unsafe { w.pwidth().bits(0b01).mwidth().bits(0b01) });
+
+        DmaLcd { lcd, dma }
+    }
+
+    /// Waits for the running transfer and releases the LCD and the DMA
+    /// channel
+    pub fn free(mut self) -> (Lcd, C2) {
+        self.wait();
+        self.dma.unlisten(Event::TransferComplete);
+        (self.lcd, self.dma)
+    }
+
+    /// Waits for the running transfer and gives access to the LCD driver
+    pub fn lcd(&mut self) -> &mut Lcd {
+        self.wait();
+        &mut self.lcd
+    }
+
+    /// Returns `true` while a transfer is running
+    pub fn is_busy(&self) -> bool {
+        finish();
+        BUSY.load(Ordering::Acquire)
+    }
+
+    /// Waits for the running transfer to finish
+    pub fn wait(&self) {
+        while self.is_busy() {}
+    }
+
+    /// Calls `callback` from [`on_interrupt`] when a transfer finishes
+    ///
+    /// The `DMA0_CHANNEL2` interrupt has to be enabled in the ECLIC.
+    pub fn listen(&mut self, callback: fn()) {
+        interrupt::free(|| unsafe {
+            CALLBACK = Some(callback);
+        });
+        self.dma.listen(Event::TransferComplete);
+    }
+
+    /// Stops calling the callback
+    pub fn unlisten(&mut self) {
+        self.dma.unlisten(Event::TransferComplete);
+        interrupt::free(|| unsafe {
+            CALLBACK = None;
+        });
+    }
+
+    /// Fills `area` with `color`
+    pub fn fill_solid(&mut self, area: &Rectangle, color: Rgb565) -> Result<(), ()> {
+        let len = match self.set_window(area)? {
+            Some(len) => len,
+            None => return Ok(()),
+        };
+        unsafe {
+            FILL_COLOR = color.into_storage();
+            self.start(&FILL_COLOR as *const u16 as u32, len, false);
+        }
+        Ok(())
+    }
+
+    /// Copies `pixels`, RGB565 values in row order, to `area`
+    ///
+    /// `area` has to be inside the screen.
+    ///
+    /// # Panics
+    ///
+    /// If the number of pixels does not match `area`.
+    pub fn write_pixels(&mut self, area: &Rectangle, pixels: &'static [u16]) -> Result<(), ()> {
+        assert_eq!(pixels.len(), (area.size.width * area.size.height) as usize);
+        if self.set_window(area)?.is_some() {
+            self.start(pixels.as_ptr() as u32, pixels.len(), true);
+        }
+        Ok(())
+    }
+
+    /// Waits for the running transfer and prepares the LCD to receive the
+    /// pixels of `area`, clipped to the screen
+    ///
+    /// Returns the number of pixels, or `None` if nothing is visible.
+    pub(super) fn set_window(&mut self, area: &Rectangle) -> Result<Option<usize>, ()> {
+        self.wait();
+
+        let area = area.intersection(&self.lcd.bounding_box());
+        let bottom_right = match area.bottom_right() {
+            Some(point) => point,
+            None => return Ok(None),
+        };
+        // Sets the window and starts a memory write without sending pixels
+        self.lcd.set_pixels(
+            area.top_left.x as u16,
+            area.top_left.y as u16,
+            bottom_right.x as u16,
+            bottom_right.y as u16,
+            core::iter::empty(),
+        )?;
+        Ok(Some((area.size.width * area.size.height) as usize))
+    }
+
+    /// Sends `len` pixels starting at `address`
+    ///
+    /// The previous transfer has to be finished.
+    pub(super) fn start(&mut self, address: u32, len: usize, increment: bool) {
+        let spi = unsafe { &*SPI0::ptr() };
+        while spi.stat.read().trans().bit_is_set() {}
+        spi.ctl0.modify(|_, w| w.spien().clear_bit());
+        spi.ctl0.modify(|_, w| w.ff16().set_bit().spien().set_bit());
+        spi.ctl1.modify(|_, w| w.dmaten().set_bit());
+
+        unsafe {
+            self.dma.set_memory_address(address, increment);
+        }
+        self.dma.set_transfer_length(len);
+        BUSY.store(true, Ordering::Release);
+        self.dma.start();
+    }
+}
+
+/// Finishes the transfer, must be called from the `DMA0_CHANNEL2`
+/// interrupt handler when a callback is registered
+pub fn on_interrupt() {
+    if finish() {
+        if let Some(callback) = unsafe { CALLBACK } {
+            callback();
+        }
+    }
+}
+
+/// Returns `true` if a transfer has just finished, after switching SPI0
+/// back to normal operation
+fn finish() -> bool {
+    interrupt::free(|| {
+        if !BUSY.load(Ordering::Acquire) {
+            return false;
+        }
+        let dma = unsafe { &*DMA0::ptr() };
+        if dma.intf.read().ftfif2().bit_is_clear() {
+            return false;
+        }
+        dma.intc.write(|w| w.gifc2().set_bit());
+        dma.ch2ctl.modify(|_, w| w.chen().clear_bit());
+
+        let spi = unsafe { &*SPI0::ptr() };
+        while spi.stat.read().tbe().bit_is_clear() || spi.stat.read().trans().bit_is_set() {}
+        spi.ctl1.modify(|_, w| w.dmaten().clear_bit());
+        spi.ctl0.modify(|_, w| w.spien().clear_bit());
+        spi.ctl0.modify(|_, w| w.ff16().clear_bit().spien().set_bit());
+        // Received data was ignored, clear the overrun error
+        let _ = spi.data.read();
+        let _ = spi.stat.read();
+
+        BUSY.store(false, Ordering::Release);
+        true
+    })
+}
@@ -0,0 +1,143 @@
+//! Full-screen fault report
+//!
+//! Used by the `panic-lcd` panic handler, and usable from trap handlers of
+//! the application. The LCD is re-initialised from scratch, so it works no
+//! matter what state the application left SPI0 and the display in.
+
+use core::fmt::{self, Write};
+use embedded_graphics::mono_font::{ascii::FONT_4X6, MonoTextStyle};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+use gd32vf103xx_hal::pac;
+use gd32vf103xx_hal::prelude::*;
+use riscv::register::{mcause, mepc, mtval};
+
+use super::Lcd;
+use crate::lcd_pins;
+
+const BACKGROUND: Rgb565 = Rgb565::new(0, 0, 20);
+const CHAR_WIDTH: i32 = 4;
+const CHAR_HEIGHT: i32 = 6;
+
+/// Trap registers shown below the message
+#[derive(Clone, Copy, Debug)]
+pub struct TrapInfo {
+    pub mcause: usize,
+    pub mepc: usize,
+    pub mtval: usize,
+}
+
+impl TrapInfo {
+    /// Reads the registers of the trap being handled
+    pub fn read() -> Self {
+        TrapInfo {
+            mcause: mcause::read().bits(),
+            mepc: mepc::read(),
+            mtval: mtval::read(),
+        }
+    }
+}
+
+/// Re-initialises the LCD and shows `title`, `message` and the trap
+/// registers, if any, in white on blue
+///
+/// Text that does not fit on the screen is cut off.
+///
+/// # Safety
+///
+/// SPI0, GPIOA, GPIOB, AFIO and the clock configuration are taken over
+/// without ownership. The application must not run again afterwards.
+pub unsafe fn show(title: &str, message: fmt::Arguments, trap: Option<&TrapInfo>) {
+    let dp = pac::Peripherals::steal();
+    // The LCD needs a fast enough APB2 clock for its SPI frequency
+    let mut rcu = dp.RCU.configure().sysclk(108.mhz()).freeze();
+    let mut afio = dp.AFIO.constrain(&mut rcu);
+    let gpioa = dp.GPIOA.split(&mut rcu);
+    let gpiob = dp.GPIOB.split(&mut rcu);
+    let lcd_pins = lcd_pins!(gpioa, gpiob);
+    let mut lcd = super::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
+
+    let size = lcd.size();
+    let _ = Rectangle::new(Point::zero(), size)
+        .into_styled(PrimitiveStyle::with_fill(BACKGROUND))
+        .draw(&mut lcd);
+    let _ = Rectangle::new(Point::zero(), Size::new(size.width, CHAR_HEIGHT as u32 + 2))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+        .draw(&mut lcd);
+
+    let mut screen = Screen {
+        columns: size.width as i32 / CHAR_WIDTH,
+        rows: (size.height as i32 - 3) / CHAR_HEIGHT,
+        lcd: &mut lcd,
+        color: BACKGROUND,
+        x_offset: 1,
+        y_offset: 1,
+        column: 0,
+        row: 0,
+    };
+    let _ = screen.write_str(title);
+
+    screen.color = Rgb565::WHITE;
+    screen.y_offset = 3;
+    screen.new_line();
+    let _ = screen.write_fmt(message);
+
+    if let Some(trap) = trap {
+        screen.new_line();
+        let _ = write!(
+            screen,
+            "\nmcause {:08x}\nmepc   {:08x}\nmtval  {:08x}",
+            trap.mcause, trap.mepc, trap.mtval
+        );
+    }
+}
+
+/// Character grid on top of the LCD
+struct Screen<'a> {
+    lcd: &'a mut Lcd,
+    color: Rgb565,
+    columns: i32,
+    rows: i32,
+    x_offset: i32,
+    y_offset: i32,
+    column: i32,
+    row: i32,
+}
+
+impl Screen<'_> {
+    fn new_line(&mut self) {
+        self.column = 0;
+        self.row += 1;
+    }
+}
+
+impl Write for Screen<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let style = MonoTextStyle::new(&FONT_4X6, self.color);
+        for c in s.chars() {
+            if c == '\n' {
+                self.new_line();
+                continue;
+            }
+            if self.column == self.columns {
+                self.new_line();
+            }
+            if self.row >= self.rows {
+                return Ok(());
+            }
+
+            let c = if c.is_ascii() && !c.is_ascii_control() { c } else { '?' };
+            let mut buf = [0; 4];
+            let position = Point::new(
+                self.x_offset + self.column * CHAR_WIDTH,
+                self.y_offset + self.row * CHAR_HEIGHT,
+            );
+            let _ = Text::with_baseline(c.encode_utf8(&mut buf), position, style, Baseline::Top)
+                .draw(self.lcd);
+            self.column += 1;
+        }
+        Ok(())
+    }
+}
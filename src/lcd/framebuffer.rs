@@ -0,0 +1,317 @@
+//! Palette-indexed framebuffer
+
+use core::convert::Infallible;
+use embedded_graphics::pixelcolor::raw::RawU8;
+use embedded_graphics::pixelcolor::{IntoStorage, Rgb565, Rgb888};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use super::console::ansi::color_256;
+use super::screenshot::{self, Format};
+use super::{DmaLcd, Lcd};
+
+/// Highest number of rows tracked for flushing
+const MAX_ROWS: usize = 160;
+
+/// Longest row that can be flushed by DMA
+const LINE_LEN: usize = 160;
+
+/// Rows expanded to RGB565 for DMA, one is filled while the other is sent
+static mut LINES: [[u16; LINE_LEN]; 2] = [[0; LINE_LEN]; 2];
+
+/// Colour of a framebuffer pixel, an index into its palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct PaletteIndex(pub u8);
+
+impl PixelColor for PaletteIndex {
+    type Raw = RawU8;
+}
+
+/// Number of bits per pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Depth {
+    /// 16 colours, two pixels per byte
+    Bpp4,
+    /// 256 colours
+    Bpp8,
+}
+
+impl Depth {
+    /// Size in bytes of a buffer of `size` pixels
+    pub const fn buffer_len(self, size: Size) -> usize {
+        let pixels = size.width as usize * size.height as usize;
+        match self {
+            Depth::Bpp4 => (pixels + 1) / 2,
+            Depth::Bpp8 => pixels,
+        }
+    }
+}
+
+/// Off-screen image drawn with embedded-graphics and copied to the LCD with
+/// [`flush`](Framebuffer::flush)
+///
+/// Pixels hold palette indices, which are expanded to RGB565 when flushing.
+/// Only the rows changed since the last flush are sent. The palette starts
+/// out as the xterm 256 colour palette, so the first 16 entries are the
+/// standard ANSI colours.
+///
+/// ```
+/// static mut PIXELS: [u8; 6400] = [0; 6400];
+///
+/// let mut fb = Framebuffer::new(unsafe { &mut PIXELS }, Depth::Bpp4, lcd.size());
+/// fb.set_color(1, Rgb565::CSS_ORANGE);
+/// Circle::new(Point::new(60, 20), 40)
+///     .into_styled(PrimitiveStyle::with_fill(PaletteIndex(1)))
+///     .draw(&mut fb)
+///     .unwrap();
+/// fb.flush(&mut lcd).unwrap();
+/// ```
+pub struct Framebuffer<'a> {
+    pixels: &'a mut [u8],
+    depth: Depth,
+    size: Size,
+    palette: [Rgb565; 256],
+    dirty: [u32; MAX_ROWS / 32],
+}
+
+impl<'a> Framebuffer<'a> {
+    /// Creates a framebuffer of `size` pixels, cleared to index 0
+    ///
+    /// # Panics
+    ///
+    /// If `pixels` is shorter than [`Depth::buffer_len`], or `size` has more
+    /// than 160 rows.
+    pub fn new(pixels: &'a mut [u8], depth: Depth, size: Size) -> Self {
+        assert!(pixels.len() >= depth.buffer_len(size));
+        assert!(size.height as usize <= MAX_ROWS);
+
+        let mut palette = [Rgb565::BLACK; 256];
+        for (index, color) in palette.iter_mut().enumerate() {
+            *color = color_256(index as u16);
+        }
+
+        let mut framebuffer = Framebuffer {
+            pixels,
+            depth,
+            size,
+            palette,
+            dirty: [0; MAX_ROWS / 32],
+        };
+        let _ = framebuffer.clear(PaletteIndex(0));
+        framebuffer
+    }
+
+    /// Returns the number of bits per pixel
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// Changes a palette entry
+    ///
+    /// The whole screen is redrawn on the next flush.
+    pub fn set_color(&mut self, index: u8, color: Rgb565) {
+        self.palette[index as usize] = color;
+        self.invalidate();
+    }
+
+    /// Returns a palette entry
+    pub fn color(&self, index: u8) -> Rgb565 {
+        self.palette[index as usize]
+    }
+
+    /// Returns the pixel at `point`, or `None` if it is outside
+    pub fn pixel(&self, point: Point) -> Option<PaletteIndex> {
+        let offset = self.offset(point)?;
+        Some(PaletteIndex(self.read(offset)))
+    }
+
+    /// Marks every row as changed, e.g. after the LCD has been drawn to
+    /// directly
+    pub fn invalidate(&mut self) {
+        self.mark_rows(0, self.size.height as usize);
+    }
+
+    /// Sends the changed rows to the LCD
+    pub fn flush(&mut self, lcd: &mut Lcd) -> Result<(), ()> {
+        let width = self.size.width as usize;
+        let mut row = 0;
+        while let Some((start, end)) = self.next_dirty_rows(row) {
+            let colors = (start * width..end * width)
+                .map(|offset| self.palette[self.read(offset) as usize].into_storage());
+            lcd.set_pixels(0, start as u16, width as u16 - 1, end as u16 - 1, colors)?;
+            row = end;
+        }
+        self.dirty = [0; MAX_ROWS / 32];
+        Ok(())
+    }
+
+    /// Sends the changed rows to the LCD by DMA
+    ///
+    /// A row is expanded to RGB565 while the previous one is sent. The last
+    /// row is still being sent when this returns.
+    ///
+    /// # Panics
+    ///
+    /// If the framebuffer is wider than 160 pixels.
+    pub fn flush_dma(&mut self, lcd: &mut DmaLcd) -> Result<(), ()> {
+        let width = self.size.width as usize;
+        assert!(width <= LINE_LEN);
+
+        let mut row = 0;
+        let mut line = 0;
+        while let Some((start, end)) = self.next_dirty_rows(row) {
+            let area = Rectangle::new(
+                Point::new(0, start as i32),
+                Size::new(width as u32, (end - start) as u32),
+            );
+            // Also waits for the last transfer, so both lines are free
+            lcd.set_window(&area)?;
+
+            for row in start..end {
+                let buffer = unsafe { &mut LINES[line] };
+                for (x, pixel) in buffer[..width].iter_mut().enumerate() {
+                    *pixel = self.palette[self.read(row * width + x) as usize].into_storage();
+                }
+                lcd.wait();
+                lcd.start(buffer.as_ptr() as u32, width, true);
+                line ^= 1;
+            }
+            row = end;
+        }
+        self.dirty = [0; MAX_ROWS / 32];
+        Ok(())
+    }
+
+    /// Encodes the framebuffer as an image and passes it to `write`, see
+    /// [`screenshot::capture`]
+    ///
+    /// # Panics
+    ///
+    /// If the framebuffer is wider than 160 pixels.
+    pub fn screenshot<E, W>(&self, format: Format, write: W) -> Result<(), screenshot::Error<E>>
+    where
+        W: FnMut(&[u8]) -> Result<(), E>,
+    {
+        let width = self.size.width as usize;
+        screenshot::encode(self.size, format, write, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+                let index = self.read(y as usize * width + x);
+                let color = Rgb888::from(self.palette[index as usize]);
+                pixel.copy_from_slice(&[color.r(), color.g(), color.b()]);
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the first run of changed rows at or after `row`
+    fn next_dirty_rows(&self, mut row: usize) -> Option<(usize, usize)> {
+        let height = self.size.height as usize;
+        while row < height && !self.is_dirty(row) {
+            row += 1;
+        }
+        let start = row;
+        while row < height && self.is_dirty(row) {
+            row += 1;
+        }
+        if start < row {
+            Some((start, row))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pixel offset of `point`
+    fn offset(&self, point: Point) -> Option<usize> {
+        let (x, y) = (point.x as u32, point.y as u32);
+        if point.x < 0 || point.y < 0 || x >= self.size.width || y >= self.size.height {
+            return None;
+        }
+        Some(y as usize * self.size.width as usize + x as usize)
+    }
+
+    fn read(&self, offset: usize) -> u8 {
+        match self.depth {
+            Depth::Bpp4 => (self.pixels[offset / 2] >> (offset % 2 * 4)) & 0x0f,
+            Depth::Bpp8 => self.pixels[offset],
+        }
+    }
+
+    fn write(&mut self, offset: usize, index: u8) {
+        match self.depth {
+            Depth::Bpp4 => {
+                let shift = offset % 2 * 4;
+                let byte = &mut self.pixels[offset / 2];
+                *byte = *byte & !(0x0f << shift) | (index & 0x0f) << shift;
+            }
+            Depth::Bpp8 => self.pixels[offset] = index,
+        }
+    }
+
+    fn is_dirty(&self, row: usize) -> bool {
+        self.dirty[row / 32] & 1 << (row % 32) != 0
+    }
+
+    fn mark_rows(&mut self, start: usize, end: usize) {
+        for row in start..end {
+            self.dirty[row / 32] |= 1 << (row % 32);
+        }
+    }
+}
+
+impl OriginDimensions for Framebuffer<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for Framebuffer<'_> {
+    type Color = PaletteIndex;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(offset) = self.offset(point) {
+                self.write(offset, color.0);
+                let row = point.y as usize;
+                self.mark_rows(row, row + 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let bottom_right = match area.bottom_right() {
+            Some(point) => point,
+            None => return Ok(()),
+        };
+
+        for y in area.top_left.y..=bottom_right.y {
+            let row = y as usize * self.size.width as usize;
+            let (start, end) = (row + area.top_left.x as usize, row + bottom_right.x as usize + 1);
+            if self.depth == Depth::Bpp8 {
+                self.pixels[start..end].fill(color.0);
+            } else {
+                for offset in start..end {
+                    self.write(offset, color.0);
+                }
+            }
+        }
+        self.mark_rows(area.top_left.y as usize, bottom_right.y as usize + 1);
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let byte = match self.depth {
+            Depth::Bpp4 => (color.0 & 0x0f) * 0x11,
+            Depth::Bpp8 => color.0,
+        };
+        let len = self.depth.buffer_len(self.size);
+        self.pixels[..len].fill(byte);
+        self.invalidate();
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Compressed images
+
+use embedded_graphics::image::ImageDrawable;
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+/// Length of the header before the palette
+const HEADER_LEN: usize = 6;
+
+/// Run-length encoded image, optionally with a palette, as produced from
+/// PNG and BMP files
+///
+/// Pixels are decoded while they are sent, so the image is streamed to the
+/// LCD without a buffer. The images are converted by the `longan-nano-rle`
+/// crate, either from the command line or as a build dependency called from
+/// the build script of the application:
+///
+/// ```ignore
+/// // build.rs
+/// let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+/// longan_nano_rle::convert_dir("assets", &out_dir).unwrap();
+/// ```
+///
+/// ```
+/// const FERRIS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/ferris.rle"));
+///
+/// let ferris = RleImage::new(FERRIS).unwrap();
+/// Image::new(&ferris, Point::new(37, 8)).draw(&mut lcd).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RleImage<'a> {
+    size: Size,
+    /// RGB565 colours, empty if the pixels are colours themselves
+    palette: &'a [u8],
+    runs: &'a [u8],
+}
+
+impl<'a> RleImage<'a> {
+    /// Reads the header of an encoded image
+    ///
+    /// Returns `None` if `data` is too short or the encoding is unknown.
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        let header = data.get(..HEADER_LEN)?;
+        let width = u16::from_le_bytes([header[0], header[1]]);
+        let height = u16::from_le_bytes([header[2], header[3]]);
+        let palette_len = match header[4] {
+            0 => 0,
+            1 => (header[5] as usize + 1) * 2,
+            _ => return None,
+        };
+        let palette = data.get(HEADER_LEN..HEADER_LEN + palette_len)?;
+
+        Some(RleImage {
+            size: Size::new(width.into(), height.into()),
+            palette,
+            runs: &data[HEADER_LEN + palette_len..],
+        })
+    }
+
+    /// Returns the pixel colours in row order
+    ///
+    /// Decoding stops early if the data is truncated.
+    pub fn colors(&self) -> Colors<'a> {
+        Colors {
+            palette: self.palette,
+            runs: self.runs,
+            remaining: 0,
+            repeat: None,
+        }
+    }
+}
+
+impl OriginDimensions for RleImage<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl ImageDrawable for RleImage<'_> {
+    type Color = Rgb565;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        target.fill_contiguous(&self.bounding_box(), self.colors())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        // The whole image has to be decoded, only the area reaches the target
+        let mut translated = target.translated(-area.top_left);
+        self.draw(&mut translated.clipped(area))
+    }
+}
+
+/// Iterator over the colours of an [`RleImage`]
+#[derive(Clone, Debug)]
+pub struct Colors<'a> {
+    palette: &'a [u8],
+    runs: &'a [u8],
+    /// Pixels left in the current run
+    remaining: u8,
+    /// Colour of the current run, `None` for a literal run
+    repeat: Option<Rgb565>,
+}
+
+impl Colors<'_> {
+    /// Reads the next pixel value from the data
+    fn read_pixel(&mut self) -> Option<Rgb565> {
+        let raw = if self.palette.is_empty() {
+            let bytes = self.runs.get(..2)?;
+            let raw = u16::from_le_bytes([bytes[0], bytes[1]]);
+            self.runs = &self.runs[2..];
+            raw
+        } else {
+            let index = *self.runs.first()? as usize * 2;
+            self.runs = &self.runs[1..];
+            let bytes = self.palette.get(index..index + 2)?;
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        };
+        Some(RawU16::new(raw).into())
+    }
+}
+
+impl Iterator for Colors<'_> {
+    type Item = Rgb565;
+
+    fn next(&mut self) -> Option<Rgb565> {
+        if self.remaining == 0 {
+            let (&control, runs) = self.runs.split_first()?;
+            self.runs = runs;
+            self.remaining = (control & 0x7f) + 1;
+            self.repeat = None;
+            if control & 0x80 != 0 {
+                self.repeat = Some(self.read_pixel()?);
+            }
+        }
+
+        self.remaining -= 1;
+        match self.repeat {
+            Some(color) => Some(color),
+            None => self.read_pixel(),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Images read from the SD card
+//!
+//! BMP and QOI files are decoded while they are read and sent to the LCD one
+//! row at a time, so only a chunk of the file and a row of pixels are held
+//! in memory whatever the image size.
+
+use core::fmt::Debug;
+use embedded_graphics::prelude::*;
+use embedded_sdmmc::{BlockDevice, Controller, File, TimeSource, Volume};
+
+use super::Lcd;
+
+/// Bytes read from the file at once, reading less than a block would read
+/// the same block several times
+const CHUNK_LEN: usize = 512;
+
+/// Widest visible part of an image
+const MAX_WIDTH: usize = 160;
+
+/// Image file formats
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Bmp,
+    Qoi,
+}
+
+/// Image file error
+#[derive(Debug)]
+pub enum Error<E: Debug> {
+    /// Reading the file failed
+    SdCard(embedded_sdmmc::Error<E>),
+    /// The file is not a BMP or QOI image, or is truncated
+    Format,
+    /// The image uses a BMP variant that is not supported
+    Unsupported,
+    /// Communication with the LCD failed
+    Lcd,
+}
+
+/// Draws the BMP or QOI image in `file` with its top left corner at
+/// `top_left`, and returns the image size
+///
+/// The format is recognised from the file content. The parts of the image
+/// outside the screen are skipped, and transparency is ignored.
+///
+/// ```
+/// let mut file = sdcard
+///     .open_file_in_dir(&mut volume, &root, "FERRIS.QOI", Mode::ReadOnly)
+///     .unwrap();
+/// image_file::draw(&mut lcd, &mut sdcard, &volume, &mut file, Point::zero()).unwrap();
+/// sdcard.close_file(&volume, file).unwrap();
+/// ```
+pub fn draw<D, T>(
+    lcd: &mut Lcd,
+    sdcard: &mut Controller<D, T>,
+    volume: &Volume,
+    file: &mut File,
+    top_left: Point,
+) -> Result<Size, Error<D::Error>>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let mut reader = Reader {
+        sdcard,
+        volume,
+        file,
+        buffer: [0; CHUNK_LEN],
+        pos: 0,
+        len: 0,
+    };
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic[..2])?;
+    let format = if magic[..2] == *b"BM" {
+        Format::Bmp
+    } else {
+        reader.read_exact(&mut magic[2..])?;
+        if magic != *b"qoif" {
+            return Err(Error::Format);
+        }
+        Format::Qoi
+    };
+
+    let mut target = Target::new(lcd, top_left);
+    match format {
+        Format::Bmp => draw_bmp(&mut reader, &mut target),
+        Format::Qoi => draw_qoi(&mut reader, &mut target),
+    }
+}
+
+/// BMP pixel layouts
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BmpPixels {
+    Rgb555,
+    Rgb565,
+    Bgr888,
+}
+
+/// Draws a BMP image, the `BM` signature has been read
+fn draw_bmp<D, T>(reader: &mut Reader<D, T>, target: &mut Target) -> Result<Size, Error<D::Error>>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    // File header, then the fields of BITMAPINFOHEADER that matter
+    let mut header = [0; 52];
+    reader.read_exact(&mut header)?;
+    let u16_at = |offset: usize| u16::from_le_bytes([header[offset - 2], header[offset - 1]]);
+    let u32_at = |offset: usize| u32::from_le_bytes([
+        header[offset - 2],
+        header[offset - 1],
+        header[offset],
+        header[offset + 1],
+    ]);
+    let pixels_offset = u32_at(10);
+    let header_len = u32_at(14);
+    let width = u32_at(18) as i32;
+    let height = u32_at(22) as i32;
+    let bpp = u16_at(28);
+    let compression = u32_at(30);
+    let mut read = 2 + header.len() as u32;
+
+    if header_len < 40 {
+        return Err(Error::Unsupported);
+    }
+    let pixels = match (bpp, compression) {
+        (16, 0) => BmpPixels::Rgb555,
+        (16, 3) => {
+            // The red mask comes right after BITMAPINFOHEADER, either on
+            // its own or as part of a newer header
+            let mut red_mask = [0; 4];
+            reader.read_exact(&mut red_mask)?;
+            read += 4;
+            if u32::from_le_bytes(red_mask) == 0xf800 {
+                BmpPixels::Rgb565
+            } else {
+                BmpPixels::Rgb555
+            }
+        }
+        (24, 0) => BmpPixels::Bgr888,
+        _ => return Err(Error::Unsupported),
+    };
+    if width <= 0 || height == 0 || pixels_offset < read {
+        return Err(Error::Format);
+    }
+    reader.skip(pixels_offset - read)?;
+
+    // Rows are stored bottom-up unless the height is negative
+    let size = Size::new(width as u32, height.unsigned_abs());
+    target.set_size(size);
+    let pixel_len = if pixels == BmpPixels::Bgr888 { 3 } else { 2 };
+    let padding = (4 - size.width * pixel_len % 4) % 4;
+    for row in 0..size.height {
+        let y = if height < 0 { row } else { size.height - 1 - row };
+        for _ in 0..size.width {
+            let color = match pixels {
+                BmpPixels::Bgr888 => {
+                    let mut bgr = [0; 3];
+                    reader.read_exact(&mut bgr)?;
+                    rgb888(bgr[2], bgr[1], bgr[0])
+                }
+                _ => {
+                    let mut raw = [0; 2];
+                    reader.read_exact(&mut raw)?;
+                    let raw = u16::from_le_bytes(raw);
+                    if pixels == BmpPixels::Rgb565 {
+                        raw
+                    } else {
+                        // Widen green to 6 bits
+                        let green = raw >> 5 & 0x1f;
+                        (raw & 0x7c00) << 1 | green << 6 | green >> 4 << 5 | raw & 0x1f
+                    }
+                }
+            };
+            target.push(color);
+        }
+        reader.skip(padding)?;
+        target.end_row(y)?;
+    }
+    Ok(size)
+}
+
+/// Draws a QOI image, the `qoif` signature has been read
+fn draw_qoi<D, T>(reader: &mut Reader<D, T>, target: &mut Target) -> Result<Size, Error<D::Error>>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let mut header = [0; 10];
+    reader.read_exact(&mut header)?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    if width == 0 || height == 0 {
+        return Err(Error::Format);
+    }
+    let size = Size::new(width, height);
+    target.set_size(size);
+
+    let mut index = [[0u8; 4]; 64];
+    let mut pixel = [0, 0, 0, 255u8];
+    let mut run = 0;
+    for y in 0..height {
+        for _ in 0..width {
+            if run > 0 {
+                run -= 1;
+            } else {
+                let op = reader.byte()?;
+                match op {
+                    0xfe => {
+                        reader.read_exact(&mut pixel[..3])?;
+                    }
+                    0xff => {
+                        reader.read_exact(&mut pixel)?;
+                    }
+                    _ => match op >> 6 {
+                        0b00 => pixel = index[op as usize],
+                        0b01 => {
+                            pixel[0] = pixel[0].wrapping_add((op >> 4 & 3).wrapping_sub(2));
+                            pixel[1] = pixel[1].wrapping_add((op >> 2 & 3).wrapping_sub(2));
+                            pixel[2] = pixel[2].wrapping_add((op & 3).wrapping_sub(2));
+                        }
+                        0b10 => {
+                            let green = (op & 0x3f).wrapping_sub(32);
+                            let next = reader.byte()?;
+                            pixel[0] = pixel[0].wrapping_add(green.wrapping_add(next >> 4).wrapping_sub(8));
+                            pixel[1] = pixel[1].wrapping_add(green);
+                            pixel[2] = pixel[2].wrapping_add(green.wrapping_add(next & 0x0f).wrapping_sub(8));
+                        }
+                        _ => run = op & 0x3f,
+                    },
+                }
+                let hash = pixel[0]
+                    .wrapping_mul(3)
+                    .wrapping_add(pixel[1].wrapping_mul(5))
+                    .wrapping_add(pixel[2].wrapping_mul(7))
+                    .wrapping_add(pixel[3].wrapping_mul(11));
+                index[hash as usize % 64] = pixel;
+            }
+            target.push(rgb888(pixel[0], pixel[1], pixel[2]));
+        }
+        target.end_row(y)?;
+    }
+    Ok(size)
+}
+
+fn rgb888(r: u8, g: u8, b: u8) -> u16 {
+    (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3
+}
+
+/// Buffered reader of a file
+struct Reader<'a, D: BlockDevice, T: TimeSource> {
+    sdcard: &'a mut Controller<D, T>,
+    volume: &'a Volume,
+    file: &'a mut File,
+    buffer: [u8; CHUNK_LEN],
+    pos: usize,
+    len: usize,
+}
+
+impl<D: BlockDevice, T: TimeSource> Reader<'_, D, T> {
+    fn byte(&mut self) -> Result<u8, Error<D::Error>> {
+        if self.pos == self.len {
+            self.len = self
+                .sdcard
+                .read(self.volume, self.file, &mut self.buffer)
+                .map_err(Error::SdCard)?;
+            self.pos = 0;
+            if self.len == 0 {
+                return Err(Error::Format);
+            }
+        }
+        self.pos += 1;
+        Ok(self.buffer[self.pos - 1])
+    }
+
+    fn read_exact(&mut self, bytes: &mut [u8]) -> Result<(), Error<D::Error>> {
+        for byte in bytes {
+            *byte = self.byte()?;
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, len: u32) -> Result<(), Error<D::Error>> {
+        for _ in 0..len {
+            self.byte()?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the visible pixels of a row and sends them to the LCD
+struct Target<'a> {
+    lcd: &'a mut Lcd,
+    top_left: Point,
+    /// Visible columns of the image
+    columns: (u32, u32),
+    row: [u16; MAX_WIDTH],
+    /// Column of the next pixel
+    x: u32,
+}
+
+impl<'a> Target<'a> {
+    fn new(lcd: &'a mut Lcd, top_left: Point) -> Self {
+        Target {
+            lcd,
+            top_left,
+            columns: (0, 0),
+            row: [0; MAX_WIDTH],
+            x: 0,
+        }
+    }
+
+    /// Computes the visible columns of an image of `size` pixels
+    fn set_size(&mut self, size: Size) {
+        let screen_width = self.lcd.size().width as i32;
+        let start = (-self.top_left.x).max(0);
+        let end = (screen_width - self.top_left.x).min(size.width as i32);
+        self.columns = if start < end {
+            (start as u32, end as u32)
+        } else {
+            (0, 0)
+        };
+    }
+
+    fn push(&mut self, color: u16) {
+        let (start, end) = self.columns;
+        if self.x >= start && self.x < end {
+            self.row[(self.x - start) as usize] = color;
+        }
+        self.x += 1;
+    }
+
+    /// Sends the row that has just been pushed as image row `y`
+    fn end_row<E: Debug>(&mut self, y: u32) -> Result<(), Error<E>> {
+        self.x = 0;
+        let (start, end) = self.columns;
+        let screen_y = self.top_left.y + y as i32;
+        if start == end || screen_y < 0 || screen_y >= self.lcd.size().height as i32 {
+            return Ok(());
+        }
+
+        let x = self.top_left.x + start as i32;
+        let len = (end - start) as usize;
+        self.lcd
+            .set_pixels(
+                x as u16,
+                screen_y as u16,
+                (x as usize + len - 1) as u16,
+                screen_y as u16,
+                self.row[..len].iter().copied(),
+            )
+            .map_err(|_| Error::Lcd)
+    }
+}
@@ -0,0 +1,455 @@
+//! Configuration menus
+//!
+//! A [`Menu`] shows a list of [`Item`]s and is driven by [`Input`] events,
+//! which can come from buttons, a rotary encoder, or the serial port through
+//! [`SerialInput`]. Settings are kept in `Cell`s owned by the application,
+//! the menu changes them in place.
+//!
+//! ```
+//! let backlight = Cell::new(true);
+//! let brightness = Cell::new(80);
+//! let display = [
+//!     Item::toggle("Backlight", &backlight),
+//!     Item::number("Brightness", &brightness, 0, 100, 10),
+//! ];
+//! let items = [Item::submenu("Display", &display), Item::action("Reboot", 1)];
+//! let mut menu = Menu::new(lcd.bounding_box(), "Settings", &items);
+//!
+//! let mut keys = SerialInput::new();
+//! loop {
+//!     if let Some(input) = stdout::try_read_byte().and_then(|byte| keys.feed(byte)) {
+//!         match menu.handle(input) {
+//!             Some(Event::Activated(1)) => reboot(),
+//!             Some(Event::Changed) => apply(brightness.get()),
+//!             _ => {}
+//!         }
+//!     }
+//!     menu.draw(&mut lcd).unwrap();
+//! }
+//! ```
+
+use core::cell::Cell;
+use core::fmt::{self, Write};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use super::ui::Widget;
+
+/// Deepest level of submenus
+const MAX_DEPTH: usize = 4;
+
+/// Height of a line, the 6x10 font plus a pixel
+const LINE_HEIGHT: u32 = 11;
+
+/// Horizontal margin of the text
+const MARGIN: i32 = 2;
+
+/// Longest value shown at the right of an item, `< -2147483648 >`
+const VALUE_LEN: usize = 16;
+
+/// User input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Previous item, or increase a number
+    Up,
+    /// Next item, or decrease a number
+    Down,
+    /// Enter a submenu, flip a toggle, start or confirm editing a number,
+    /// or activate an action
+    Select,
+    /// Leave a submenu, or cancel editing a number
+    Back,
+}
+
+/// Turns characters received on the serial port into inputs
+///
+/// The arrow keys of a terminal move (up, down), select (right) and go back
+/// (left). `w`/`k` and `s`/`j` also move, enter and space select, and
+/// backspace and `q` go back. A `\n` right after a `\r` is ignored, so a
+/// terminal sending CRLF selects once.
+pub struct SerialInput {
+    state: KeyState,
+    /// Set after a `\r` so that a following `\n` doesn't select again
+    skip_lf: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyState {
+    Ground,
+    /// After `ESC`
+    Escape,
+    /// Inside `ESC [` or `ESC O`, until the final byte
+    Sequence,
+}
+
+impl SerialInput {
+    /// Creates a decoder waiting for a key
+    pub const fn new() -> Self {
+        SerialInput {
+            state: KeyState::Ground,
+            skip_lf: false,
+        }
+    }
+
+    /// Processes a received byte, returns an input when one is complete
+    pub fn feed(&mut self, byte: u8) -> Option<Input> {
+        if core::mem::replace(&mut self.skip_lf, byte == b'\r') && byte == b'\n' {
+            return None;
+        }
+        match self.state {
+            KeyState::Ground => match byte {
+                0x1b => {
+                    self.state = KeyState::Escape;
+                    None
+                }
+                b'w' | b'k' => Some(Input::Up),
+                b's' | b'j' => Some(Input::Down),
+                b'\r' | b'\n' | b' ' => Some(Input::Select),
+                0x08 | 0x7f | b'q' => Some(Input::Back),
+                _ => None,
+            },
+            KeyState::Escape => {
+                // Cursor keys send `ESC O` in the application mode
+                self.state = match byte {
+                    b'[' | b'O' => KeyState::Sequence,
+                    _ => KeyState::Ground,
+                };
+                None
+            }
+            KeyState::Sequence => {
+                // Parameters and intermediate bytes, e.g. `ESC [ 1 ; 5 A`
+                if (0x20..0x40).contains(&byte) {
+                    return None;
+                }
+                self.state = KeyState::Ground;
+                match byte {
+                    b'A' => Some(Input::Up),
+                    b'B' => Some(Input::Down),
+                    b'C' => Some(Input::Select),
+                    b'D' => Some(Input::Back),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl Default for SerialInput {
+    fn default() -> Self {
+        SerialInput::new()
+    }
+}
+
+/// What happened in reaction to an input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A toggle or a number has been changed
+    Changed,
+    /// An action item has been selected, with its id
+    Activated(u16),
+    /// Back has been pressed in the top level menu
+    Exit,
+}
+
+#[derive(Clone, Copy)]
+enum Kind<'a> {
+    Submenu(&'a [Item<'a>]),
+    Toggle(&'a Cell<bool>),
+    Number {
+        value: &'a Cell<i32>,
+        min: i32,
+        max: i32,
+        step: i32,
+    },
+    Action(u16),
+}
+
+/// Menu entry
+#[derive(Clone, Copy)]
+pub struct Item<'a> {
+    label: &'a str,
+    kind: Kind<'a>,
+}
+
+impl<'a> Item<'a> {
+    /// Entry opening another menu
+    pub const fn submenu(label: &'a str, items: &'a [Item<'a>]) -> Self {
+        Item { label, kind: Kind::Submenu(items) }
+    }
+
+    /// On/off setting, flipped when selected
+    pub const fn toggle(label: &'a str, value: &'a Cell<bool>) -> Self {
+        Item { label, kind: Kind::Toggle(value) }
+    }
+
+    /// Number from `min` to `max`, changed by `step` while editing
+    pub const fn number(label: &'a str, value: &'a Cell<i32>, min: i32, max: i32, step: i32) -> Self {
+        Item {
+            label,
+            kind: Kind::Number { value, min, max, step },
+        }
+    }
+
+    /// Entry reporting [`Event::Activated`] with `id` when selected
+    pub const fn action(label: &'a str, id: u16) -> Self {
+        Item { label, kind: Kind::Action(id) }
+    }
+
+    /// Returns the label
+    pub fn label(&self) -> &'a str {
+        self.label
+    }
+}
+
+/// Open menu and its position
+#[derive(Clone, Copy)]
+struct Level<'a> {
+    title: &'a str,
+    items: &'a [Item<'a>],
+    selected: usize,
+    /// First visible item
+    top: usize,
+}
+
+/// Hierarchical menu drawn in a rectangle of the screen
+///
+/// The first line shows the title of the open menu and the others its
+/// items, scrolling to keep the selected one visible. Like the widgets of
+/// [`ui`](super::ui), only the lines that changed are redrawn.
+pub struct Menu<'a> {
+    area: Rectangle,
+    levels: [Level<'a>; MAX_DEPTH],
+    depth: usize,
+    /// Number being edited, not yet stored
+    editing: Option<i32>,
+    foreground: Rgb565,
+    background: Rgb565,
+    highlight: Rgb565,
+    /// Bit 0 for the title, bit n for visible line n
+    dirty_lines: u32,
+}
+
+impl<'a> Menu<'a> {
+    /// Creates a menu drawn in `area`, in white on black with the selected
+    /// item on blue
+    pub fn new(area: Rectangle, title: &'a str, items: &'a [Item<'a>]) -> Self {
+        let root = Level {
+            title,
+            items,
+            selected: 0,
+            top: 0,
+        };
+        Menu {
+            area,
+            levels: [root; MAX_DEPTH],
+            depth: 0,
+            editing: None,
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+            highlight: Rgb565::BLUE,
+            dirty_lines: !0,
+        }
+    }
+
+    /// Sets the text, background and selected item colours
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565, highlight: Rgb565) {
+        self.foreground = foreground;
+        self.background = background;
+        self.highlight = highlight;
+        self.invalidate();
+    }
+
+    /// Returns to the top level menu
+    pub fn reset(&mut self) {
+        self.depth = 0;
+        self.editing = None;
+        self.invalidate();
+    }
+
+    /// Reacts to an input
+    pub fn handle(&mut self, input: Input) -> Option<Event> {
+        let level = self.levels[self.depth];
+        let item = level.items.get(level.selected)?;
+
+        if let (Some(pending), Kind::Number { value, min, max, step }) = (self.editing, item.kind) {
+            self.mark_selected();
+            match input {
+                Input::Up => self.editing = Some(pending.saturating_add(step).min(max)),
+                Input::Down => self.editing = Some(pending.saturating_sub(step).max(min)),
+                Input::Select => {
+                    self.editing = None;
+                    if pending != value.get() {
+                        value.set(pending);
+                        return Some(Event::Changed);
+                    }
+                }
+                Input::Back => self.editing = None,
+            }
+            return None;
+        }
+
+        match input {
+            Input::Up => self.select(level.selected.checked_sub(1).unwrap_or(level.items.len() - 1)),
+            Input::Down => self.select((level.selected + 1) % level.items.len()),
+            Input::Select => match item.kind {
+                Kind::Submenu(items) if !items.is_empty() && self.depth + 1 < MAX_DEPTH => {
+                    self.depth += 1;
+                    self.levels[self.depth] = Level {
+                        title: item.label,
+                        items,
+                        selected: 0,
+                        top: 0,
+                    };
+                    self.invalidate();
+                }
+                Kind::Submenu(_) => {}
+                Kind::Toggle(value) => {
+                    value.set(!value.get());
+                    self.mark_selected();
+                    return Some(Event::Changed);
+                }
+                Kind::Number { value, .. } => {
+                    self.editing = Some(value.get());
+                    self.mark_selected();
+                }
+                Kind::Action(id) => return Some(Event::Activated(id)),
+            },
+            Input::Back => {
+                if self.depth == 0 {
+                    return Some(Event::Exit);
+                }
+                self.depth -= 1;
+                self.invalidate();
+            }
+        }
+        None
+    }
+
+    /// Number of item lines below the title
+    fn visible_lines(&self) -> usize {
+        (self.area.size.height / LINE_HEIGHT).saturating_sub(1).max(1) as usize
+    }
+
+    /// Moves the selection, scrolling if needed
+    fn select(&mut self, index: usize) {
+        let lines = self.visible_lines();
+        self.mark_selected();
+        let level = &mut self.levels[self.depth];
+        level.selected = index;
+        if index < level.top {
+            level.top = index;
+        } else if index >= level.top + lines {
+            level.top = index + 1 - lines;
+        } else {
+            self.mark_selected();
+            return;
+        }
+        self.invalidate();
+    }
+
+    fn mark_selected(&mut self) {
+        let level = &self.levels[self.depth];
+        self.dirty_lines |= 1 << (level.selected - level.top + 1);
+    }
+
+    /// Draws line `line`, 0 being the title
+    fn draw_line<D>(&self, target: &mut D, line: usize) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let level = &self.levels[self.depth];
+        let area = Rectangle::new(
+            self.area.top_left + Point::new(0, (line as u32 * LINE_HEIGHT) as i32),
+            Size::new(self.area.size.width, LINE_HEIGHT),
+        );
+
+        let index = (level.top + line).wrapping_sub(1);
+        let (label, item) = match line {
+            0 => (level.title, None),
+            _ => match level.items.get(index) {
+                Some(item) => (item.label, Some(item)),
+                None => ("", None),
+            },
+        };
+        let (foreground, background) = if line == 0 {
+            (self.background, self.foreground)
+        } else if item.is_some() && index == level.selected {
+            (self.foreground, self.highlight)
+        } else {
+            (self.foreground, self.background)
+        };
+        target.fill_solid(&area, background)?;
+
+        let style = MonoTextStyle::new(&FONT_6X10, foreground);
+        let left = area.top_left + Point::new(MARGIN, 1);
+        Text::with_baseline(label, left, style, Baseline::Top).draw(target)?;
+
+        let mut value = ValueText { bytes: [0; VALUE_LEN], len: 0 };
+        let _ = match item.map(|item| item.kind) {
+            Some(Kind::Submenu(_)) => write!(value, ">"),
+            Some(Kind::Toggle(on)) => write!(value, "{}", if on.get() { "On" } else { "Off" }),
+            Some(Kind::Number { value: number, .. }) => match self.editing {
+                Some(pending) if index == level.selected => write!(value, "< {} >", pending),
+                _ => write!(value, "{}", number.get()),
+            },
+            Some(Kind::Action(_)) | None => Ok(()),
+        };
+        let right = Point::new(area.top_left.x + area.size.width as i32 - 1 - MARGIN, left.y);
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(value.as_str(), right, style, text_style).draw(target)?;
+        Ok(())
+    }
+}
+
+impl Widget for Menu<'_> {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        for line in 0..=self.visible_lines() {
+            if self.dirty_lines & 1 << line != 0 {
+                self.draw_line(target, line)?;
+            }
+        }
+        self.dirty_lines = 0;
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.dirty_lines = !0;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty_lines != 0
+    }
+}
+
+/// Value of an item, formatted on the stack
+struct ValueText {
+    bytes: [u8; VALUE_LEN],
+    len: usize,
+}
+
+impl ValueText {
+    fn as_str(&self) -> &str {
+        // Only whole strs are copied in
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for ValueText {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Power management
+
+use embedded_hal::blocking::delay::DelayMs;
+use gd32vf103xx_hal::pac::RCU;
+
+use super::command::{self, DISPOFF, DISPON, IDMOFF, IDMON, SLPIN, SLPOUT};
+use super::Lcd;
+
+/// Time the panel needs after leaving sleep before it accepts commands,
+/// which is also the shortest time between entering and leaving sleep
+const SLEEP_OUT_DELAY_MS: u32 = 120;
+
+/// Time the panel needs after entering sleep
+const SLEEP_IN_DELAY_MS: u32 = 5;
+
+/// Power saving modes of the LCD
+///
+/// ```
+/// lcd.blank_and_sleep(&mut delay);
+/// // SPI0 is stopped, the LCD must not be drawn to
+/// lcd.wake(&mut delay);
+/// ```
+pub trait Power {
+    /// Turns the display output off, the memory content is kept
+    fn display_off(&mut self);
+
+    /// Turns the display output on
+    fn display_on(&mut self);
+
+    /// Enters sleep mode, stopping the panel's oscillator and booster
+    fn sleep_in<D: DelayMs<u32>>(&mut self, delay: &mut D);
+
+    /// Leaves sleep mode and waits until the panel is ready
+    ///
+    /// At least 120 ms must have passed since [`sleep_in`](Power::sleep_in).
+    fn sleep_out<D: DelayMs<u32>>(&mut self, delay: &mut D);
+
+    /// Switches the idle mode, which shows only 8 colours to save power
+    fn set_idle_mode(&mut self, idle: bool);
+
+    /// Turns the display off, puts the panel to sleep and stops the SPI0
+    /// clock
+    ///
+    /// Nothing may be drawn before [`wake`](Power::wake) is called.
+    fn blank_and_sleep<D: DelayMs<u32>>(&mut self, delay: &mut D);
+
+    /// Undoes [`blank_and_sleep`](Power::blank_and_sleep)
+    ///
+    /// At least 120 ms must have passed since the panel was put to sleep.
+    fn wake<D: DelayMs<u32>>(&mut self, delay: &mut D);
+}
+
+impl Power for Lcd {
+    fn display_off(&mut self) {
+        command::write(self, DISPOFF, &[]);
+    }
+
+    fn display_on(&mut self) {
+        command::write(self, DISPON, &[]);
+    }
+
+    fn sleep_in<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        command::write(self, SLPIN, &[]);
+        delay.delay_ms(SLEEP_IN_DELAY_MS);
+    }
+
+    fn sleep_out<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        command::write(self, SLPOUT, &[]);
+        delay.delay_ms(SLEEP_OUT_DELAY_MS);
+    }
+
+    fn set_idle_mode(&mut self, idle: bool) {
+        command::write(self, if idle { IDMON } else { IDMOFF }, &[]);
+    }
+
+    fn blank_and_sleep<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        self.display_off();
+        self.sleep_in(delay);
+        set_spi_clock(false);
+    }
+
+    fn wake<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        set_spi_clock(true);
+        self.sleep_out(delay);
+        self.display_on();
+    }
+}
+
+/// Gates the SPI0 clock, its configuration is kept
+fn set_spi_clock(enabled: bool) {
+    let rcu = unsafe { &*RCU::ptr() };
+    riscv::interrupt::free(|| {
+        rcu.apb2en.modify(|_, w| w.spi0en().bit(enabled));
+    });
+}
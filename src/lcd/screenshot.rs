@@ -0,0 +1,223 @@
+//! Screenshots
+//!
+//! The screen is read back from the LCD controller over the MISO line, or
+//! taken from a [`Framebuffer`](super::Framebuffer), and encoded as an
+//! uncompressed image. The image is passed in chunks to a writer function,
+//! so it can go to stdout or to a file on the SD card without being held in
+//! memory.
+
+use core::fmt::{self, Write};
+use embedded_graphics::prelude::*;
+use gd32vf103xx_hal::pac::SPI0;
+
+use super::command::{self, NOP, RAMRD, RDDID};
+use super::Lcd;
+
+/// Widest screen that can be captured
+const MAX_WIDTH: usize = 160;
+
+/// SPI0 prescaler used while reading, the ST7735 reads at most at 6 MHz
+/// and 108 MHz / 64 leaves some margin
+const READ_PRESCALER: u8 = 0b101;
+
+/// Image file format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Binary portable pixmap (P6)
+    Ppm,
+    /// 24-bit Windows bitmap
+    Bmp,
+}
+
+/// Screenshot error
+#[derive(Debug)]
+pub enum Error<E> {
+    /// Communication with the LCD failed
+    Lcd,
+    /// The LCD does not answer reads, its data output is not connected
+    NoReadback,
+    /// The writer function failed
+    Write(E),
+}
+
+/// Reads the screen back from the LCD and passes the encoded image to
+/// `write`
+///
+/// SPI0 is slowed down while reading. Reading needs the panel's data output
+/// to be connected to PA6, which is not the case on the stock Longan Nano:
+/// [`Error::NoReadback`] is returned before anything is written if the
+/// display ID reads as all zeros or all ones. Use
+/// [`Framebuffer::screenshot`](super::Framebuffer::screenshot) when drawing
+/// through a framebuffer.
+///
+/// ```
+/// // Over the UART, e.g. `cat /dev/ttyUSB0 > screen.bmp`
+/// screenshot::capture(&mut lcd, Format::Bmp, |bytes| {
+///     stdout::write_bytes(bytes);
+///     Ok::<(), ()>(())
+/// })
+/// .unwrap();
+///
+/// // To the SD card
+/// let mut file = sdcard
+///     .open_file_in_dir(&mut volume, &root, "SCREEN.BMP", Mode::ReadWriteCreateOrTruncate)
+///     .unwrap();
+/// screenshot::capture(&mut lcd, Format::Bmp, |bytes| {
+///     sdcard.write(&mut volume, &mut file, bytes).map(|_| ())
+/// })
+/// .unwrap();
+/// sdcard.close_file(&volume, file).unwrap();
+/// ```
+///
+/// # Panics
+///
+/// If the screen is wider than 160 pixels.
+pub fn capture<E, W>(lcd: &mut Lcd, format: Format, write: W) -> Result<(), Error<E>>
+where
+    W: FnMut(&[u8]) -> Result<(), E>,
+{
+    let size = lcd.size();
+    let spi = unsafe { &*SPI0::ptr() };
+    let prescaler = spi.ctl0.read().psc().bits();
+    set_prescaler(prescaler.max(READ_PRESCALER));
+
+    let result = check_readback(lcd).and_then(|_| encode(size, format, write, |y, row| read_row(lcd, y, row)));
+
+    // Any command ends the memory read
+    command::write(lcd, NOP, &[]);
+    set_prescaler(prescaler);
+    result
+}
+
+/// Reads the display ID, an unconnected data line gives the same level on
+/// every bit
+fn check_readback<E>(lcd: &mut Lcd) -> Result<(), Error<E>> {
+    command::write(lcd, RDDID, &[]);
+    // A dummy clock cycle comes before the 24-bit ID, so it spans 4 bytes
+    let mut id = [0; 4];
+    for byte in id.iter_mut() {
+        *byte = command::transfer(0);
+    }
+    command::write(lcd, NOP, &[]);
+
+    if id.iter().all(|&byte| byte == 0) || id.iter().all(|&byte| byte == 0xff) {
+        Err(Error::NoReadback)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads row `y` of the LCD as RGB bytes
+fn read_row<E>(lcd: &mut Lcd, y: u32, row: &mut [u8]) -> Result<(), Error<E>> {
+    let width = row.len() as u16 / 3;
+    // Sets the window, the memory write it starts is ended by the read
+    lcd.set_pixels(0, y as u16, width - 1, y as u16, core::iter::empty())
+        .map_err(|_| Error::Lcd)?;
+    command::write(lcd, RAMRD, &[]);
+    // The first byte is a dummy read
+    command::transfer(0);
+    // Pixels come as 18-bit colour, 6 bits per component in the high bits
+    for byte in row.iter_mut() {
+        let value = command::transfer(0);
+        *byte = value & 0xfc | value >> 6;
+    }
+    Ok(())
+}
+
+/// Changes the SPI0 clock prescaler
+fn set_prescaler(prescaler: u8) {
+    let spi = unsafe { &*SPI0::ptr() };
+    while spi.stat.read().trans().bit_is_set() {}
+    spi.ctl0.modify(|_, w| w.spien().clear_bit());
+    spi.ctl0.modify(|_, w| unsafe { w.psc().bits(prescaler).spien().set_bit() });
+}
+
+/// Encodes an image of `size` pixels, `read_row` fills each row with RGB
+/// bytes
+///
+/// # Panics
+///
+/// If the image is wider than 160 pixels.
+pub(super) fn encode<E, W, R>(size: Size, format: Format, mut write: W, mut read_row: R) -> Result<(), Error<E>>
+where
+    W: FnMut(&[u8]) -> Result<(), E>,
+    R: FnMut(u32, &mut [u8]) -> Result<(), Error<E>>,
+{
+    let width = size.width as usize;
+    assert!(width <= MAX_WIDTH);
+    // BMP rows are padded to 4 bytes
+    let row_len = match format {
+        Format::Ppm => width * 3,
+        Format::Bmp => (width * 3 + 3) & !3,
+    };
+
+    let mut header = Header { bytes: [0; 54], len: 0 };
+    match format {
+        Format::Ppm => {
+            let _ = write!(header, "P6\n{} {}\n255\n", size.width, size.height);
+        }
+        Format::Bmp => header.bitmap(size, row_len),
+    }
+    write(&header.bytes[..header.len]).map_err(Error::Write)?;
+
+    let mut row = [0; MAX_WIDTH * 3 + 3];
+    for line in 0..size.height {
+        // BMP stores the bottom row first
+        let y = match format {
+            Format::Ppm => line,
+            Format::Bmp => size.height - 1 - line,
+        };
+        read_row(y, &mut row[..width * 3])?;
+        if format == Format::Bmp {
+            for pixel in row[..width * 3].chunks_exact_mut(3) {
+                pixel.swap(0, 2);
+            }
+        }
+        write(&row[..row_len]).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+/// Image file header
+struct Header {
+    bytes: [u8; 54],
+    len: usize,
+}
+
+impl Header {
+    /// Fills in the headers of a 24-bit bottom-up bitmap
+    fn bitmap(&mut self, size: Size, row_len: usize) {
+        let image_len = row_len as u32 * size.height;
+        self.push(b"BM");
+        self.push(&(54 + image_len).to_le_bytes());
+        self.push(&[0; 4]);
+        self.push(&54u32.to_le_bytes());
+        // BITMAPINFOHEADER
+        self.push(&40u32.to_le_bytes());
+        self.push(&size.width.to_le_bytes());
+        self.push(&size.height.to_le_bytes());
+        self.push(&1u16.to_le_bytes());
+        self.push(&24u16.to_le_bytes());
+        self.push(&0u32.to_le_bytes());
+        self.push(&image_len.to_le_bytes());
+        // 72 DPI
+        self.push(&2835u32.to_le_bytes());
+        self.push(&2835u32.to_le_bytes());
+        self.push(&[0; 8]);
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+impl Write for Header {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
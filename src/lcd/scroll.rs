@@ -0,0 +1,126 @@
+//! Hardware scrolling
+
+use super::command::{self, NORON, VSCRDEF, VSCSAD};
+use super::{Lcd, LcdConfig, Orientation};
+
+/// Rows of the ST7735S frame memory along the scroll axis
+const MEMORY_ROWS: u16 = 162;
+
+/// Band of the screen scrolled by the controller
+///
+/// The ST7735 scrolls along the long side of the panel: vertically in the
+/// portrait orientations and horizontally in the landscape ones. Positions
+/// are screen coordinates along that axis, y in portrait and x in
+/// landscape. Created by [`LcdConfig::scroll_area`].
+///
+/// Scrolling does not move the frame memory, it changes which part of it is
+/// shown first. After scrolling by `offset` lines, the line shown at `line`
+/// is drawn at [`draw_position(line, offset)`](ScrollArea::draw_position).
+///
+/// ```
+/// let config = LcdConfig::new();
+/// let area = config.scroll_area(0, 160);
+/// let mut lcd = lcd::configure_with(dp.SPI0, lcd_pins, config, &mut afio, &mut rcu);
+/// lcd.set_scroll_area(&area);
+///
+/// // Strip chart: shift by one column and draw the newest sample at the
+/// // right edge
+/// offset = (offset + 1) % 160;
+/// lcd.set_scroll_offset(&area, offset);
+/// let x = area.draw_position(159, offset).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ScrollArea {
+    start: u16,
+    len: u16,
+    /// Fixed rows of the frame memory before the scrolled band
+    top_fixed: u16,
+    /// Whether the orientation runs against the frame memory rows
+    mirrored: bool,
+}
+
+impl ScrollArea {
+    /// Returns the screen position where the content shown at `line` has
+    /// to be drawn, when the area is scrolled by `offset` lines
+    ///
+    /// `None` if `line` is outside the area.
+    pub fn draw_position(&self, line: u16, offset: u16) -> Option<u16> {
+        let line = line.checked_sub(self.start).filter(|&line| line < self.len)?;
+        Some(self.start + (line + offset % self.len) % self.len)
+    }
+
+    /// First line of the area
+    pub fn start(&self) -> u16 {
+        self.start
+    }
+
+    /// Number of lines of the area
+    pub fn lines(&self) -> u16 {
+        self.len
+    }
+}
+
+impl LcdConfig {
+    /// Returns the scroll area covering `len` lines from `start`
+    ///
+    /// # Panics
+    ///
+    /// If the area is empty or does not fit on the screen.
+    pub fn scroll_area(&self, start: u16, len: u16) -> ScrollArea {
+        let (width, height) = self.size();
+        let (dx, dy) = self.offset();
+        let (screen_len, offset) = if width < height { (height, dy) } else { (width, dx) };
+        assert!(len > 0 && start + len <= screen_len as u16);
+
+        let mirrored = matches!(
+            self.orientation,
+            Orientation::PortraitSwapped | Orientation::LandscapeSwapped
+        );
+        let top_fixed = if mirrored {
+            MEMORY_ROWS - offset - start - len
+        } else {
+            offset + start
+        };
+        ScrollArea { start, len, top_fixed, mirrored }
+    }
+}
+
+/// Hardware scrolling of the LCD
+pub trait Scroll {
+    /// Defines the scrolled band, the rest of the screen stays in place
+    fn set_scroll_area(&mut self, area: &ScrollArea);
+
+    /// Scrolls the area by `offset` lines
+    ///
+    /// Content moves towards the start of the area, and the lines scrolled
+    /// out come back at its end.
+    fn set_scroll_offset(&mut self, area: &ScrollArea, offset: u16);
+
+    /// Leaves scroll mode, showing the frame memory as it is
+    fn stop_scrolling(&mut self);
+}
+
+impl Scroll for Lcd {
+    fn set_scroll_area(&mut self, area: &ScrollArea) {
+        let bottom_fixed = MEMORY_ROWS - area.top_fixed - area.len;
+        let mut params = [0; 6];
+        params[..2].copy_from_slice(&area.top_fixed.to_be_bytes());
+        params[2..4].copy_from_slice(&area.len.to_be_bytes());
+        params[4..].copy_from_slice(&bottom_fixed.to_be_bytes());
+        command::write(self, VSCRDEF, &params);
+    }
+
+    fn set_scroll_offset(&mut self, area: &ScrollArea, offset: u16) {
+        let offset = offset % area.len;
+        let start = if area.mirrored {
+            area.top_fixed + (area.len - offset) % area.len
+        } else {
+            area.top_fixed + offset
+        };
+        command::write(self, VSCSAD, &start.to_be_bytes());
+    }
+
+    fn stop_scrolling(&mut self) {
+        command::write(self, NORON, &[]);
+    }
+}
@@ -0,0 +1,653 @@
+//! Dashboard widgets
+//!
+//! Widgets remember what they have drawn and only redraw what changed when
+//! [`Widget::draw`] is called, so a dashboard can be refreshed in a loop
+//! without clearing the screen. Call [`Widget::invalidate`] after drawing
+//! over a widget to have it redrawn entirely.
+//!
+//! ```
+//! let mut title = Label::new(Point::new(0, 0), "Temperature");
+//! let mut temperature = Value::new(Point::new(0, 12), 1, " C");
+//! let mut level = Bar::new(Rectangle::new(Point::new(0, 30), Size::new(160, 8)), 0, 100);
+//!
+//! lcd.clear(Rgb565::BLACK).unwrap();
+//! loop {
+//!     temperature.set_value(read_temperature());
+//!     level.set_value(read_level());
+//!     title.draw(&mut lcd).unwrap();
+//!     temperature.draw(&mut lcd).unwrap();
+//!     level.draw(&mut lcd).unwrap();
+//! }
+//! ```
+
+use core::fmt::{self, Write};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoFont, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Arc, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+
+/// Widest strip chart
+const MAX_CHART_WIDTH: usize = 160;
+
+/// Longest formatted value
+const VALUE_LEN: usize = 24;
+
+/// Element of the screen that redraws itself when it changes
+pub trait Widget {
+    /// Draws the parts of the widget that changed since the last call
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>;
+
+    /// Makes the next [`draw`](Widget::draw) redraw the whole widget
+    fn invalidate(&mut self);
+
+    /// Returns `true` if the widget has to be drawn
+    fn is_dirty(&self) -> bool;
+}
+
+/// Text drawn at a fixed position, which remembers its width so that a
+/// shorter text can erase the end of the previous one
+struct TextArea {
+    position: Point,
+    font: &'static MonoFont<'static>,
+    foreground: Rgb565,
+    background: Rgb565,
+    drawn_width: u32,
+}
+
+impl TextArea {
+    fn new(position: Point) -> Self {
+        TextArea {
+            position,
+            font: &FONT_6X10,
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+            drawn_width: 0,
+        }
+    }
+
+    fn draw<D>(&mut self, target: &mut D, text: &str) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let style = MonoTextStyleBuilder::new()
+            .font(self.font)
+            .text_color(self.foreground)
+            .background_color(self.background)
+            .build();
+        let text = Text::with_baseline(text, self.position, style, Baseline::Top);
+        let area = text.bounding_box();
+        text.draw(target)?;
+
+        if self.drawn_width > area.size.width {
+            let rest = Rectangle::new(
+                self.position + Point::new(area.size.width as i32, 0),
+                Size::new(self.drawn_width - area.size.width, self.font.character_size.height),
+            );
+            target.fill_solid(&rest, self.background)?;
+        }
+        self.drawn_width = area.size.width;
+        Ok(())
+    }
+}
+
+/// Single line of text
+pub struct Label<'a> {
+    area: TextArea,
+    text: &'a str,
+    dirty: bool,
+}
+
+impl<'a> Label<'a> {
+    /// Creates a label with its top left corner at `position`, in white on
+    /// black with a 6x10 font
+    pub fn new(position: Point, text: &'a str) -> Self {
+        Label {
+            area: TextArea::new(position),
+            text,
+            dirty: true,
+        }
+    }
+
+    /// Changes the text
+    pub fn set_text(&mut self, text: &'a str) {
+        if text != self.text {
+            self.text = text;
+            self.dirty = true;
+        }
+    }
+
+    /// Returns the text
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Sets the text and background colours
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.area.foreground = foreground;
+        self.area.background = background;
+        self.dirty = true;
+    }
+
+    /// Sets the font
+    pub fn set_font(&mut self, font: &'static MonoFont<'static>) {
+        self.area.font = font;
+        self.dirty = true;
+    }
+}
+
+impl Widget for Label<'_> {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if self.dirty {
+            self.area.draw(target, self.text)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+/// Number with a fixed number of decimals and a unit
+///
+/// The value is an integer in units of the last decimal, e.g. 1234 with 2
+/// decimals is shown as `12.34`.
+pub struct Value {
+    area: TextArea,
+    value: i32,
+    decimals: u8,
+    unit: &'static str,
+    dirty: bool,
+}
+
+impl Value {
+    /// Creates a readout with its top left corner at `position`, in white
+    /// on black with a 6x10 font, showing 0
+    ///
+    /// `decimals` is at most 9.
+    pub fn new(position: Point, decimals: u8, unit: &'static str) -> Self {
+        Value {
+            area: TextArea::new(position),
+            value: 0,
+            decimals: decimals.min(9),
+            unit,
+            dirty: true,
+        }
+    }
+
+    /// Changes the value
+    pub fn set_value(&mut self, value: i32) {
+        if value != self.value {
+            self.value = value;
+            self.dirty = true;
+        }
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Sets the text and background colours
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.area.foreground = foreground;
+        self.area.background = background;
+        self.dirty = true;
+    }
+
+    /// Sets the font
+    pub fn set_font(&mut self, font: &'static MonoFont<'static>) {
+        self.area.font = font;
+        self.dirty = true;
+    }
+}
+
+impl Widget for Value {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut text = TextBuffer { bytes: [0; VALUE_LEN], len: 0 };
+        let sign = if self.value < 0 { "-" } else { "" };
+        let magnitude = self.value.unsigned_abs();
+        let _ = match self.decimals {
+            0 => write!(text, "{}{}{}", sign, magnitude, self.unit),
+            decimals => {
+                let divisor = 10u32.pow(decimals.into());
+                write!(
+                    text,
+                    "{}{}.{:0width$}{}",
+                    sign,
+                    magnitude / divisor,
+                    magnitude % divisor,
+                    self.unit,
+                    width = decimals as usize
+                )
+            }
+        };
+        self.area.draw(target, text.as_str())?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+/// Text formatted on the stack, cut short if it does not fit
+struct TextBuffer {
+    bytes: [u8; VALUE_LEN],
+    len: usize,
+}
+
+impl TextBuffer {
+    fn as_str(&self) -> &str {
+        // Only whole strs are copied in
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for TextBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+/// Returns the fraction of `range` covered by `value`, scaled to `scale`
+fn scale(value: i32, (min, max): (i32, i32), scale: u32) -> u32 {
+    if max <= min {
+        return 0;
+    }
+    let value = value.max(min).min(max);
+    ((value as i64 - min as i64) * scale as i64 / (max as i64 - min as i64)) as u32
+}
+
+/// Horizontal bar filled from the left in proportion to a value
+pub struct Bar {
+    area: Rectangle,
+    range: (i32, i32),
+    value: i32,
+    foreground: Rgb565,
+    background: Rgb565,
+    /// Width of the filled part on the screen, `None` if nothing is drawn
+    drawn: Option<u32>,
+}
+
+impl Bar {
+    /// Creates a bar covering `area`, showing values from `min` to `max`,
+    /// in white on dark grey
+    pub fn new(area: Rectangle, min: i32, max: i32) -> Self {
+        Bar {
+            area,
+            range: (min, max),
+            value: min,
+            foreground: Rgb565::WHITE,
+            background: Rgb565::CSS_DIM_GRAY,
+            drawn: None,
+        }
+    }
+
+    /// Changes the value, it is clamped to the range of the bar
+    pub fn set_value(&mut self, value: i32) {
+        self.value = value;
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Sets the colours of the filled and empty parts
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.foreground = foreground;
+        self.background = background;
+        self.drawn = None;
+    }
+
+    fn filled_width(&self) -> u32 {
+        scale(self.value, self.range, self.area.size.width)
+    }
+}
+
+impl Widget for Bar {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let width = self.filled_width();
+        // Only the part between the old and new ends changes
+        let (start, end, color) = match self.drawn {
+            None => {
+                target.fill_solid(&self.area, self.background)?;
+                (0, width, self.foreground)
+            }
+            Some(drawn) if width > drawn => (drawn, width, self.foreground),
+            Some(drawn) => (width, drawn, self.background),
+        };
+        if start < end {
+            let part = Rectangle::new(
+                self.area.top_left + Point::new(start as i32, 0),
+                Size::new(end - start, self.area.size.height),
+            );
+            target.fill_solid(&part, color)?;
+        }
+        self.drawn = Some(width);
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.drawn != Some(self.filled_width())
+    }
+}
+
+/// Arc covering three quarters of a circle, filled clockwise from the
+/// bottom left in proportion to a value
+pub struct Gauge {
+    center: Point,
+    diameter: u32,
+    thickness: u32,
+    range: (i32, i32),
+    value: i32,
+    foreground: Rgb565,
+    background: Rgb565,
+    /// Filled angle on the screen in degrees, `None` if nothing is drawn
+    drawn: Option<u32>,
+}
+
+impl Gauge {
+    /// Angle of the empty gauge, clockwise from 3 o'clock
+    const START: f32 = 135.0;
+    /// Angle covered by the full gauge
+    const SWEEP: u32 = 270;
+
+    /// Creates a gauge centred on `center`, showing values from `min` to
+    /// `max`, in white on dark grey
+    ///
+    /// `thickness` is the width of the arc, it is drawn inside `diameter`.
+    pub fn new(center: Point, diameter: u32, thickness: u32, min: i32, max: i32) -> Self {
+        Gauge {
+            center,
+            diameter,
+            thickness,
+            range: (min, max),
+            value: min,
+            foreground: Rgb565::WHITE,
+            background: Rgb565::CSS_DIM_GRAY,
+            drawn: None,
+        }
+    }
+
+    /// Changes the value, it is clamped to the range of the gauge
+    pub fn set_value(&mut self, value: i32) {
+        self.value = value;
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Sets the colours of the filled and empty parts
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.foreground = foreground;
+        self.background = background;
+        self.drawn = None;
+    }
+
+    fn filled_angle(&self) -> u32 {
+        scale(self.value, self.range, Self::SWEEP)
+    }
+
+    /// Draws the part of the arc from `start` to `end` degrees
+    fn draw_arc<D>(&self, target: &mut D, start: u32, end: u32, color: Rgb565) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if start >= end {
+            return Ok(());
+        }
+        // The stroke is centred on the arc, keep it inside the diameter
+        let diameter = self.diameter.saturating_sub(self.thickness);
+        Arc::with_center(
+            self.center,
+            diameter,
+            (Self::START + start as f32).deg(),
+            ((end - start) as f32).deg(),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(color, self.thickness))
+        .draw(target)
+    }
+}
+
+impl Widget for Gauge {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let angle = self.filled_angle();
+        match self.drawn {
+            None => {
+                self.draw_arc(target, 0, angle, self.foreground)?;
+                self.draw_arc(target, angle, Self::SWEEP, self.background)?;
+            }
+            Some(drawn) if angle > drawn => self.draw_arc(target, drawn, angle, self.foreground)?,
+            Some(drawn) => self.draw_arc(target, angle, drawn, self.background)?,
+        }
+        self.drawn = Some(angle);
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.drawn != Some(self.filled_angle())
+    }
+}
+
+/// Rows covered by a column of a strip chart, from top to bottom inclusive
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Span {
+    top: u8,
+    bottom: u8,
+}
+
+/// Line chart of the latest samples, scrolling left as samples are added
+///
+/// Each column shows one sample, the newest one at the right edge. Only the
+/// columns whose line changed are redrawn.
+pub struct StripChart {
+    area: Rectangle,
+    range: (i32, i32),
+    foreground: Rgb565,
+    background: Rgb565,
+    /// Ring buffer of samples, `head` is the oldest one once it is full
+    samples: [i32; MAX_CHART_WIDTH],
+    head: usize,
+    len: usize,
+    /// What is on the screen in each column, `None` for nothing
+    drawn: [Option<Span>; MAX_CHART_WIDTH],
+    /// Set when the area has to be cleared
+    cleared: bool,
+    dirty: bool,
+}
+
+impl StripChart {
+    /// Creates an empty chart covering `area`, showing values from `min` at
+    /// the bottom to `max` at the top, in white on black
+    ///
+    /// # Panics
+    ///
+    /// If `area` is empty, wider than 160 or taller than 256 pixels.
+    pub fn new(area: Rectangle, min: i32, max: i32) -> Self {
+        let Size { width, height } = area.size;
+        assert!(width > 0 && width as usize <= MAX_CHART_WIDTH && height > 0 && height <= 256);
+        StripChart {
+            area,
+            range: (min, max),
+            foreground: Rgb565::WHITE,
+            background: Rgb565::BLACK,
+            samples: [0; MAX_CHART_WIDTH],
+            head: 0,
+            len: 0,
+            drawn: [None; MAX_CHART_WIDTH],
+            cleared: false,
+            dirty: true,
+        }
+    }
+
+    /// Adds a sample, dropping the oldest one if the chart is full
+    pub fn push(&mut self, sample: i32) {
+        let width = self.area.size.width as usize;
+        if self.len < width {
+            self.samples[(self.head + self.len) % width] = sample;
+            self.len += 1;
+        } else {
+            self.samples[self.head] = sample;
+            self.head = (self.head + 1) % width;
+        }
+        self.dirty = true;
+    }
+
+    /// Removes all the samples
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.dirty = true;
+    }
+
+    /// Sets the line and background colours
+    pub fn set_colors(&mut self, foreground: Rgb565, background: Rgb565) {
+        self.foreground = foreground;
+        self.background = background;
+        self.invalidate();
+    }
+
+    /// Returns the sample shown in `column`
+    fn sample(&self, column: usize) -> Option<i32> {
+        let width = self.area.size.width as usize;
+        let first = width - self.len;
+        if column < first {
+            return None;
+        }
+        Some(self.samples[(self.head + column - first) % width])
+    }
+
+    /// Returns the row of `sample`
+    fn row(&self, sample: i32) -> u8 {
+        let height = self.area.size.height - 1;
+        (height - scale(sample, self.range, height)) as u8
+    }
+
+    /// Returns the rows covered in `column`, the line goes from the previous
+    /// sample to the one of the column
+    fn span(&self, column: usize) -> Option<Span> {
+        let row = self.row(self.sample(column)?);
+        let previous = column
+            .checked_sub(1)
+            .and_then(|column| self.sample(column))
+            .map_or(row, |sample| self.row(sample));
+        Some(Span {
+            top: row.min(previous),
+            bottom: row.max(previous),
+        })
+    }
+
+    /// Fills rows `top..=bottom` of `column`
+    fn fill_rows<D>(&self, target: &mut D, column: usize, top: u8, bottom: u8, color: Rgb565) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if top > bottom {
+            return Ok(());
+        }
+        let rows = Rectangle::new(
+            self.area.top_left + Point::new(column as i32, top.into()),
+            Size::new(1, (bottom - top) as u32 + 1),
+        );
+        target.fill_solid(&rows, color)
+    }
+}
+
+impl Widget for StripChart {
+    fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if !self.cleared {
+            target.fill_solid(&self.area, self.background)?;
+            self.drawn = [None; MAX_CHART_WIDTH];
+            self.cleared = true;
+        }
+        if !self.dirty {
+            return Ok(());
+        }
+
+        for column in 0..self.area.size.width as usize {
+            let span = self.span(column);
+            let drawn = self.drawn[column];
+            if span == drawn {
+                continue;
+            }
+            // Erase the rows of the old line that are not part of the new one
+            if let Some(old) = drawn {
+                match span {
+                    Some(new) if old.bottom >= new.top && new.bottom >= old.top => {
+                        if old.top < new.top {
+                            self.fill_rows(target, column, old.top, new.top - 1, self.background)?;
+                        }
+                        if old.bottom > new.bottom {
+                            self.fill_rows(target, column, new.bottom + 1, old.bottom, self.background)?;
+                        }
+                    }
+                    _ => self.fill_rows(target, column, old.top, old.bottom, self.background)?,
+                }
+            }
+            if let Some(new) = span {
+                self.fill_rows(target, column, new.top, new.bottom, self.foreground)?;
+            }
+            self.drawn[column] = span;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn invalidate(&mut self) {
+        self.cleared = false;
+        self.dirty = true;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty || !self.cleared
+    }
+}
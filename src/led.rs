@@ -3,50 +3,185 @@
 //! - Red = PC13
 //! - Green = PA1
 //! - Blue = PA2
+//!
+//! [`PinLed`] drives LEDs connected to any other output pin.
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+use embedded_hal::Pwm;
 use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin};
+use gd32vf103xx_hal::afio::Afio;
 use gd32vf103xx_hal::gpio::gpioc::PC13;
-use gd32vf103xx_hal::gpio::gpioa::{PA1, PA2};
-use gd32vf103xx_hal::gpio::{Output, PushPull, Active};
+use gd32vf103xx_hal::gpio::gpioa::{PA0, PA1, PA2, PA3};
+use gd32vf103xx_hal::gpio::{Alternate, Output, PushPull, Active, State};
+use gd32vf103xx_hal::pac::{GPIOC, TIMER1};
+use gd32vf103xx_hal::pwm::{Channel, NoRemap, PwmTimer};
+use gd32vf103xx_hal::rcu::Rcu;
+use gd32vf103xx_hal::time::U32Ext;
+
+mod pattern;
+
+pub use pattern::Pattern;
+
+/// LED polarity
+pub trait Polarity {
+    /// `true` if the LED is lit when the pin is high
+    const ACTIVE_HIGH: bool;
+}
+
+/// LED is lit when the pin is high
+pub struct ActiveHigh;
+
+impl Polarity for ActiveHigh {
+    const ACTIVE_HIGH: bool = true;
+}
+
+/// LED is lit when the pin is low
+pub struct ActiveLow;
+
+impl Polarity for ActiveLow {
+    const ACTIVE_HIGH: bool = false;
+}
 
-/// Red LED
-pub struct RED {
-    port: PC13<Output<PushPull>>
+/// LED connected to an output pin
+///
+/// ```
+/// let mut led: PinLed<_, ActiveHigh> = PinLed::new(gpiob.pb8.into_push_pull_output());
+/// led.on();
+/// ```
+pub struct PinLed<P, POL = ActiveHigh> {
+    pin: P,
+    _polarity: PhantomData<POL>,
 }
 
-impl RED {
-    pub fn new<T: Active>(port: PC13<T>) -> Self {
+impl<P, POL> PinLed<P, POL> {
+    pub fn new(pin: P) -> Self {
         Self {
-            port: port.into_push_pull_output()
+            pin,
+            _polarity: PhantomData,
         }
     }
+
+    /// Releases the pin
+    pub fn free(self) -> P {
+        self.pin
+    }
 }
 
-/// Green LED
-pub struct GREEN {
-    port: PA1<Output<PushPull>>
+impl<P: OutputPin, POL: Polarity> PinLed<P, POL> {
+    /// Turns the LED off
+    pub fn try_off(&mut self) -> Result<(), P::Error> {
+        if POL::ACTIVE_HIGH {
+            self.pin.set_low()
+        } else {
+            self.pin.set_high()
+        }
+    }
+
+    /// Turns the LED on
+    pub fn try_on(&mut self) -> Result<(), P::Error> {
+        if POL::ACTIVE_HIGH {
+            self.pin.set_high()
+        } else {
+            self.pin.set_low()
+        }
+    }
 }
 
-impl GREEN {
-    pub fn new<T: Active>(port: PA1<T>) -> Self {
-        Self {
-            port: port.into_push_pull_output()
+impl<P: StatefulOutputPin, POL: Polarity> PinLed<P, POL> {
+    /// Checks the LED status
+    pub fn try_is_on(&self) -> Result<bool, P::Error> {
+        if POL::ACTIVE_HIGH {
+            self.pin.is_set_high()
+        } else {
+            self.pin.is_set_low()
+        }
+    }
+
+    /// Toggles the LED
+    pub fn try_toggle(&mut self) -> Result<(), P::Error> {
+        if self.try_is_on()? {
+            self.try_off()
+        } else {
+            self.try_on()
         }
     }
 }
 
-/// Blue LED
-pub struct BLUE {
-    port: PA2<Output<PushPull>>
+impl<P, POL> Led for PinLed<P, POL>
+where P: StatefulOutputPin<Error = Infallible>, POL: Polarity
+{
+    fn off(&mut self) {
+        infallible(self.try_off())
+    }
+
+    fn on(&mut self) {
+        infallible(self.try_on())
+    }
+
+    fn is_on(&mut self) -> bool {
+        infallible(self.try_is_on())
+    }
+}
+
+fn infallible<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => match e {},
+    }
 }
 
-impl BLUE {
-    pub fn new<T: Active>(port: PA2<T>) -> Self {
-        Self {
-            port: port.into_push_pull_output()
+macro_rules! onboard_led {
+    ($(#[$attr:meta])* $name:ident: $PXi:ident) => {
+        $(#[$attr])*
+        pub struct $name {
+            led: PinLed<$PXi<Output<PushPull>>, ActiveLow>
         }
-    }
+
+        impl $name {
+            pub fn new<T: Active>(port: $PXi<T>) -> Self {
+                Self {
+                    led: PinLed::new(port.into_push_pull_output())
+                }
+            }
+
+            /// Releases the pin
+            pub fn free(self) -> $PXi<Output<PushPull>> {
+                self.led.free()
+            }
+        }
+
+        impl Led for $name {
+            fn off(&mut self) {
+                self.led.off()
+            }
+
+            fn on(&mut self) {
+                self.led.on()
+            }
+
+            fn is_on(&mut self) -> bool {
+                self.led.is_on()
+            }
+        }
+    };
 }
 
+onboard_led!(
+    /// Red LED
+    RED: PC13
+);
+
+onboard_led!(
+    /// Green LED
+    GREEN: PA1
+);
+
+onboard_led!(
+    /// Blue LED
+    BLUE: PA2
+);
+
 /// Returns RED, GREEN and BLUE LEDs.
 pub fn rgb<X, Y, Z>(
     red: PC13<X>, green: PA1<Y>, blue: PA2<Z>
@@ -69,46 +204,174 @@ pub trait Led {
 
     /// Checks the LED status
     fn is_on(&mut self) -> bool;
+
+    /// Toggles the LED
+    fn toggle(&mut self) {
+        if self.is_on() {
+            self.off();
+        } else {
+            self.on();
+        }
+    }
 }
 
-impl Led for RED {
-    fn off(&mut self) {
-        self.port.set_high().unwrap();
+/// LED with adjustable brightness
+pub trait Dimmable {
+    /// Sets the brightness, 0 is off and 255 is full brightness
+    fn set_brightness(&mut self, level: u8);
+}
+
+/// Brightness of the red channel, shared with the TIMER1 interrupt
+static RED_DUTY: AtomicU8 = AtomicU8::new(0);
+
+/// RGB LED with 8-bit brightness per channel
+///
+/// Green (PA1) and blue (PA2) are driven by the TIMER1 channel 1 and 2 PWM
+/// outputs. Red (PC13) has no timer output, so it is switched in software:
+/// on at the TIMER1 update event and off at the channel 0 compare event.
+/// [`RgbLed::on_interrupt`] must be called from the `TIMER1` interrupt
+/// handler and the interrupt must be enabled in the ECLIC:
+///
+/// ```
+/// let mut led = RgbLed::new(gpioc.pc13, gpioa.pa1, gpioa.pa2, dp.TIMER1, &mut afio, &mut rcu);
+/// ECLIC::setup(Interrupt::TIMER1, TriggerType::Level, Level::L1, Priority::P1);
+/// unsafe {
+///     ECLIC::unmask(Interrupt::TIMER1);
+///     riscv::interrupt::enable();
+/// }
+/// led.set_color(255, 128, 0);
+///
+/// #[no_mangle]
+/// fn TIMER1() {
+///     RgbLed::on_interrupt();
+/// }
+/// ```
+pub struct RgbLed {
+    pwm: PwmTimer<TIMER1, NoRemap>,
+    _red: PC13<Output<PushPull>>,
+    _green: PA1<Alternate<PushPull>>,
+    _blue: PA2<Alternate<PushPull>>,
+    color: (u8, u8, u8),
+    brightness: u8,
+}
+
+impl RgbLed {
+    /// PWM frequency
+    pub const FREQUENCY_HZ: u32 = 1_000;
+
+    pub fn new<X, Y, Z>(
+        red: PC13<X>, green: PA1<Y>, blue: PA2<Z>,
+        timer: TIMER1, afio: &mut Afio, rcu: &mut Rcu
+    ) -> Self
+    where X: Active, Y: Active, Z: Active
+    {
+        let red = red.into_push_pull_output_with_state(State::High);
+        let green = green.into_alternate_push_pull();
+        let blue = blue.into_alternate_push_pull();
+
+        let mut pwm = PwmTimer::<TIMER1, NoRemap>::new::<NoRemap>(
+            timer,
+            (None::<&PA0<Alternate<PushPull>>>, Some(&green), Some(&blue), None::<&PA3<Alternate<PushPull>>>),
+            rcu,
+            afio,
+        );
+        pwm.set_period(Self::FREQUENCY_HZ.hz());
+
+        let timer = unsafe { &*TIMER1::ptr() };
+        // The leds are active-low: make the outputs low while CNT < CHxCV
+        timer.chctl2.modify(|_, w| w.ch1p().set_bit().ch2p().set_bit());
+        timer.dmainten.modify(|_, w| w.upie().set_bit().ch0ie().set_bit());
+
+        let mut led = RgbLed {
+            pwm,
+            _red: red,
+            _green: green,
+            _blue: blue,
+            color: (0, 0, 0),
+            brightness: u8::MAX,
+        };
+        led.set_color(0, 0, 0);
+        led.pwm.enable(Channel::CH1);
+        led.pwm.enable(Channel::CH2);
+        led
     }
 
-    fn on(&mut self) {
-        self.port.set_low().unwrap();
+    /// Sets the brightness of all three channels
+    pub fn set_color(&mut self, r: u8, g: u8, b: u8) {
+        self.set_red(r);
+        self.set_green(g);
+        self.set_blue(b);
     }
 
-    fn is_on(&mut self) -> bool {
-        self.port.is_set_low().unwrap()
+    /// Returns the current (red, green, blue) brightness
+    pub fn color(&self) -> (u8, u8, u8) {
+        self.color
     }
-}
 
-impl Led for GREEN {
-    fn off(&mut self) {
-        self.port.set_high().unwrap();
+    /// Sets the brightness of the red channel
+    pub fn set_red(&mut self, value: u8) {
+        self.color.0 = value;
+        let value = self.scale(value);
+        let duty = self.duty(value);
+        self.pwm.set_duty(Channel::CH0, duty);
+        RED_DUTY.store(value, Ordering::Relaxed);
     }
 
-    fn on(&mut self) {
-        self.port.set_low().unwrap();
+    /// Sets the brightness of the green channel
+    pub fn set_green(&mut self, value: u8) {
+        self.color.1 = value;
+        let duty = self.duty(self.scale(value));
+        self.pwm.set_duty(Channel::CH1, duty);
     }
 
-    fn is_on(&mut self) -> bool {
-        self.port.is_set_low().unwrap()
+    /// Sets the brightness of the blue channel
+    pub fn set_blue(&mut self, value: u8) {
+        self.color.2 = value;
+        let duty = self.duty(self.scale(value));
+        self.pwm.set_duty(Channel::CH2, duty);
     }
-}
 
-impl Led for BLUE {
-    fn off(&mut self) {
-        self.port.set_high().unwrap();
+    /// Returns the overall brightness
+    pub fn brightness(&self) -> u8 {
+        self.brightness
     }
 
-    fn on(&mut self) {
-        self.port.set_low().unwrap();
+    fn scale(&self, value: u8) -> u8 {
+        (value as u16 * self.brightness as u16 / 255) as u8
     }
 
-    fn is_on(&mut self) -> bool {
-        self.port.is_set_low().unwrap()
+    fn duty(&self, value: u8) -> u16 {
+        (self.pwm.get_max_duty() as u32 * value as u32 / 255) as u16
+    }
+
+    /// Drives the red channel, must be called from the `TIMER1` interrupt handler
+    pub fn on_interrupt() {
+        let timer = unsafe { &*TIMER1::ptr() };
+        let gpioc = unsafe { &*GPIOC::ptr() };
+        let duty = RED_DUTY.load(Ordering::Relaxed);
+
+        let intf = timer.intf.read();
+        if intf.upif().bit_is_set() {
+            // The flags are cleared by writing 0, writing 1 has no effect
+            timer.intf.write(|w| unsafe { w.bits(0xffff) }.upif().clear_bit());
+            if duty != 0 {
+                gpioc.bop.write(|w| w.cr13().set_bit());
+            }
+        }
+        if intf.ch0if().bit_is_set() {
+            timer.intf.write(|w| unsafe { w.bits(0xffff) }.ch0if().clear_bit());
+            if duty != u8::MAX {
+                gpioc.bop.write(|w| w.bop13().set_bit());
+            }
+        }
+    }
+}
+
+impl Dimmable for RgbLed {
+    /// Scales all three channels of the current colour
+    fn set_brightness(&mut self, level: u8) {
+        self.brightness = level;
+        let (r, g, b) = self.color;
+        self.set_color(r, g, b);
     }
 }
@@ -0,0 +1,269 @@
+//! Non-blocking LED patterns
+
+use super::{Dimmable, Led};
+
+/// Default interval between two [`Pattern::tick`] calls
+pub const DEFAULT_TICK_MS: u16 = 10;
+
+/// Heartbeat: two short pulses followed by a long pause
+const HEARTBEAT: [(bool, u16); 4] = [(true, 100), (false, 100), (true, 100), (false, 700)];
+
+/// Morse codes for `A`..=`Z` followed by `0`..=`9`
+const MORSE: [&str; 36] = [
+    ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---",
+    "-.-", ".-..", "--", "-.", "---", ".--.", "--.-", ".-.", "...", "-",
+    "..-", "...-", ".--", "-..-", "-.--", "--..",
+    "-----", ".----", "..---", "...--", "....-",
+    ".....", "-....", "--...", "---..", "----.",
+];
+
+fn morse_code(c: u8) -> Option<&'static [u8]> {
+    let index = match c {
+        b'a'..=b'z' => c - b'a',
+        b'A'..=b'Z' => c - b'A',
+        b'0'..=b'9' => c - b'0' + 26,
+        _ => return None,
+    };
+    Some(MORSE[index as usize].as_bytes())
+}
+
+#[derive(Copy, Clone)]
+enum Kind<'a> {
+    Blink { times: u16, on_ms: u16, off_ms: u16 },
+    Heartbeat,
+    Morse { text: &'a [u8], unit_ms: u16 },
+    Breathe { period_ms: u16 },
+}
+
+/// Position inside a Morse message
+#[derive(Copy, Clone, Default)]
+struct MorseCursor {
+    chr: usize,
+    elem: usize,
+    gap: bool,
+}
+
+impl MorseCursor {
+    /// Returns the next (on, length in units) step, `None` at the end of the text
+    fn next(&mut self, text: &[u8]) -> Option<(bool, u16)> {
+        loop {
+            let c = *text.get(self.chr)?;
+            if c == b' ' {
+                self.chr += 1;
+                // Word gap is 7 units, 3 of them are already part of the letter gap
+                return Some((false, 4));
+            }
+            let code = match morse_code(c) {
+                Some(code) => code,
+                None => {
+                    self.chr += 1;
+                    continue;
+                }
+            };
+
+            if self.gap {
+                self.gap = false;
+                if self.elem < code.len() {
+                    return Some((false, 1));
+                }
+                self.chr += 1;
+                self.elem = 0;
+                return Some((false, 3));
+            }
+
+            let units = if code[self.elem] == b'-' { 3 } else { 1 };
+            self.elem += 1;
+            self.gap = true;
+            return Some((true, units));
+        }
+    }
+}
+
+/// LED pattern sequencer
+///
+/// The pattern advances by a fixed interval on every [`Pattern::tick`]
+/// call, so it can be driven from the main loop or a timer interrupt
+/// without blocking:
+///
+/// ```
+/// let mut timer = Timer::timer2(dp.TIMER2, 100.hz(), &mut rcu);
+/// let mut pattern = Pattern::heartbeat();
+/// loop {
+///     if timer.wait().is_ok() {
+///         pattern.tick(&mut red);
+///     }
+///     // do other work
+/// }
+/// ```
+pub struct Pattern<'a> {
+    kind: Kind<'a>,
+    tick_ms: u16,
+    step: u32,
+    morse: MorseCursor,
+    on: bool,
+    remaining: i32,
+    elapsed: u32,
+    finished: bool,
+}
+
+impl<'a> Pattern<'a> {
+    fn new(kind: Kind<'a>) -> Self {
+        let mut pattern = Pattern {
+            kind,
+            tick_ms: DEFAULT_TICK_MS,
+            step: 0,
+            morse: MorseCursor::default(),
+            on: false,
+            remaining: 0,
+            elapsed: 0,
+            finished: false,
+        };
+        pattern.restart();
+        pattern
+    }
+
+    /// Blinks `times` times, or forever if `times` is zero
+    pub fn blink(times: u16, on_ms: u16, off_ms: u16) -> Self {
+        Self::new(Kind::Blink { times, on_ms, off_ms })
+    }
+
+    /// Repeats a heartbeat pulse forever
+    pub fn heartbeat() -> Self {
+        Self::new(Kind::Heartbeat)
+    }
+
+    /// Repeats `text` in Morse code forever
+    ///
+    /// `unit_ms` is the length of a dot. Characters other than letters,
+    /// digits and spaces are skipped.
+    pub fn morse(text: &'a str, unit_ms: u16) -> Self {
+        Self::new(Kind::Morse { text: text.as_bytes(), unit_ms })
+    }
+
+    /// Repeats SOS in Morse code forever
+    pub fn sos() -> Self {
+        Self::morse("SOS", 150)
+    }
+
+    /// Fades in and out forever
+    ///
+    /// Use [`Pattern::tick_dimmable`] to drive the LED, on/off LEDs are
+    /// on for the brighter half of the period.
+    pub fn breathe(period_ms: u16) -> Self {
+        Self::new(Kind::Breathe { period_ms })
+    }
+
+    /// Sets the interval between two `tick` calls
+    pub fn tick_period(mut self, ms: u16) -> Self {
+        self.tick_ms = ms;
+        self
+    }
+
+    /// Starts the pattern from the beginning
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.morse = MorseCursor::default();
+        self.elapsed = 0;
+        self.finished = false;
+        self.on = false;
+        self.remaining = 0;
+        if !matches!(self.kind, Kind::Breathe { .. }) {
+            self.advance();
+        }
+    }
+
+    /// Returns `true` once a finite pattern has completed
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advances the pattern and switches `led` accordingly
+    pub fn tick<L: Led + ?Sized>(&mut self, led: &mut L) {
+        if self.next_level() >= 128 {
+            led.on();
+        } else {
+            led.off();
+        }
+    }
+
+    /// Advances the pattern and sets the brightness of `led`
+    pub fn tick_dimmable<L: Dimmable + ?Sized>(&mut self, led: &mut L) {
+        let level = self.next_level();
+        led.set_brightness(level);
+    }
+
+    /// Returns the brightness for the current tick and advances the pattern
+    pub fn next_level(&mut self) -> u8 {
+        if self.finished {
+            return 0;
+        }
+
+        if let Kind::Breathe { period_ms } = self.kind {
+            let period = u32::from(period_ms.max(2));
+            let t = self.elapsed % period;
+            self.elapsed = (t + u32::from(self.tick_ms)) % period;
+
+            // Triangle wave, squared to look linear to the eye
+            let half = period / 2;
+            let x = if t < half { t * 255 / half } else { (period - t) * 255 / (period - half) };
+            let x = x.min(255);
+            return (x * x / 255) as u8;
+        }
+
+        let level = if self.on { u8::MAX } else { 0 };
+        self.remaining -= i32::from(self.tick_ms);
+        while self.remaining <= 0 && !self.finished {
+            self.advance();
+        }
+        level
+    }
+
+    /// Loads the next step into `on` and `remaining`
+    fn advance(&mut self) {
+        match self.step() {
+            Some((on, ms)) => {
+                self.on = on;
+                self.remaining += i32::from(ms.max(1));
+            }
+            None => {
+                self.on = false;
+                self.finished = true;
+            }
+        }
+    }
+
+    /// Returns the next (on, duration in ms) step
+    fn step(&mut self) -> Option<(bool, u16)> {
+        let index = self.step;
+        self.step = self.step.wrapping_add(1);
+
+        match self.kind {
+            Kind::Blink { times, on_ms, off_ms } => {
+                if times != 0 && index / 2 >= u32::from(times) {
+                    return None;
+                }
+                if index % 2 == 0 {
+                    Some((true, on_ms))
+                } else {
+                    Some((false, off_ms))
+                }
+            }
+            Kind::Heartbeat => Some(HEARTBEAT[index as usize % HEARTBEAT.len()]),
+            Kind::Morse { text, unit_ms } => {
+                let (on, units) = match self.morse.next(text) {
+                    Some(step) => step,
+                    None => {
+                        // Nothing to send at all
+                        if index == 0 {
+                            return None;
+                        }
+                        self.morse = MorseCursor::default();
+                        (false, 4)
+                    }
+                };
+                Some((on, units.saturating_mul(unit_ms)))
+            }
+            Kind::Breathe { .. } => None,
+        }
+    }
+}
@@ -5,11 +5,23 @@
 
 pub use gd32vf103xx_hal as hal;
 
+pub mod board;
+#[cfg(feature = "fault")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fault")))]
+pub mod fault;
 #[cfg(feature = "lcd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "lcd")))]
 pub mod lcd;
 pub mod led;
+#[cfg(feature = "fault")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fault")))]
+pub mod mem;
+#[cfg(any(feature = "panic-stdout", feature = "panic-lcd", feature = "fault"))]
+mod panic;
+pub mod rtc;
 pub mod stdout;
 #[cfg(feature = "sdcard")]
 #[cfg_attr(docsrs, doc(cfg(feature = "sdcard")))]
 pub mod sdcard;
+
+pub use board::Board;
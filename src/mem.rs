@@ -0,0 +1,93 @@
+//! Fault-safe memory probing
+//!
+//! Accesses to unmapped addresses or absent peripherals raise an access
+//! fault. The probes below are recognised by the exception handler of
+//! [`crate::fault`], which makes them fail instead of halting.
+//!
+//! ```
+//! // Does this chip have a second CAN controller?
+//! let has_can1 = mem::probe_read32(0x4000_6800).is_some();
+//! ```
+
+use core::ptr;
+
+use crate::fault::{Fault, TrapFrame};
+
+#[cfg(target_arch = "riscv32")]
+core::arch::global_asm!(
+    "
+    .section .text.__longan_nano_probe, \"ax\"
+    .global __longan_nano_probe_read32
+    .global __longan_nano_probe_read32_access
+    .global __longan_nano_probe_write32
+    .global __longan_nano_probe_write32_access
+
+    // a0: address, returns the value in a0 and a fault flag in a1
+__longan_nano_probe_read32:
+    li a1, 0
+__longan_nano_probe_read32_access:
+    lw a0, 0(a0)
+    ret
+
+    // a0: address, a1: value, returns a fault flag in a0
+__longan_nano_probe_write32:
+    mv a2, a0
+    li a0, 0
+__longan_nano_probe_write32_access:
+    sw a1, 0(a2)
+    ret
+"
+);
+
+extern "C" {
+    fn __longan_nano_probe_read32(address: usize) -> u64;
+    fn __longan_nano_probe_write32(address: usize, value: u32) -> u32;
+    static __longan_nano_probe_read32_access: u8;
+    static __longan_nano_probe_write32_access: u8;
+}
+
+/// Reads a word, returns `None` if the access faults
+///
+/// `address` must be 4-byte aligned. Reading some peripheral registers has
+/// side effects, such as clearing status flags.
+pub fn probe_read32(address: usize) -> Option<u32> {
+    let result = unsafe { __longan_nano_probe_read32(address) };
+    if result >> 32 == 0 {
+        Some(result as u32)
+    } else {
+        None
+    }
+}
+
+/// Writes a word, returns `false` if the access faults
+///
+/// `address` must be 4-byte aligned.
+///
+/// # Safety
+///
+/// The write must not break memory safety, e.g. by overwriting memory in use
+/// or reconfiguring peripherals owned by drivers.
+pub unsafe fn probe_write32(address: usize, value: u32) -> bool {
+    __longan_nano_probe_write32(address, value) == 0
+}
+
+/// Called by the exception handler, makes a faulting probe fail
+///
+/// Returns the address to resume at if the exception was raised by a probe.
+///
+/// # Safety
+///
+/// `frame` must point to the trap frame saved on exception entry.
+pub(crate) unsafe fn recover(fault: &Fault, frame: *mut TrapFrame) -> Option<usize> {
+    let read_access = &__longan_nano_probe_read32_access as *const u8 as usize;
+    let write_access = &__longan_nano_probe_write32_access as *const u8 as usize;
+
+    if fault.mepc == read_access {
+        ptr::addr_of_mut!((*frame).a1).write(1);
+    } else if fault.mepc == write_access {
+        ptr::addr_of_mut!((*frame).a0).write(1);
+    } else {
+        return None;
+    }
+    Some(fault.next_instruction())
+}
@@ -0,0 +1,74 @@
+//! Panic handler
+//!
+//! With `panic-stdout`, the panic message and location are printed through
+//! [`crate::stdout`]. If stdout has not been configured, USART0 is taken over
+//! and set up at 115200 bps. With `panic-lcd`, they are shown on the LCD, see
+//! [`crate::lcd::fault_screen`]. Both features can be enabled together.
+//! Afterwards the red LED blinks SOS forever.
+//!
+//! The reporting and halting code is shared with [`crate::fault`].
+
+#[cfg(any(feature = "panic-stdout", feature = "panic-lcd"))]
+use core::panic::PanicInfo;
+use gd32vf103xx_hal::delay::McycleDelay;
+use gd32vf103xx_hal::pac;
+use gd32vf103xx_hal::prelude::*;
+
+use crate::led::{Pattern, RED};
+#[cfg(any(feature = "panic-stdout", feature = "fault"))]
+use crate::stdout;
+
+#[cfg(any(feature = "panic-stdout", feature = "panic-lcd"))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    unsafe {
+        riscv::interrupt::disable();
+    }
+
+    #[cfg(feature = "panic-stdout")]
+    report_stdout(info);
+
+    #[cfg(feature = "panic-lcd")]
+    unsafe {
+        crate::lcd::fault_screen::show("PANIC", format_args!("{}", info), None);
+    }
+
+    signal_forever()
+}
+
+#[cfg(feature = "panic-stdout")]
+fn report_stdout(info: &PanicInfo) {
+    configure_stdout();
+    crate::sprintln!("\n{}", info);
+    stdout::flush();
+}
+
+/// Takes over USART0 at 115200 bps if stdout has not been configured
+#[cfg(any(feature = "panic-stdout", feature = "fault"))]
+pub(crate) fn configure_stdout() {
+    if !stdout::is_configured() {
+        // NOTE(unsafe) the application never runs again
+        let dp = unsafe { pac::Peripherals::steal() };
+        let mut rcu = dp.RCU.configure().freeze();
+        let mut afio = dp.AFIO.constrain(&mut rcu);
+        let gpioa = dp.GPIOA.split(&mut rcu);
+        stdout::configure(dp.USART0, gpioa.pa9, gpioa.pa10, 115_200.bps(), &mut afio, &mut rcu);
+    }
+}
+
+/// Blinks SOS on the red LED forever
+pub(crate) fn signal_forever() -> ! {
+    // The clock configuration of the application is unknown, switch back to
+    // the internal oscillator to get a known delay
+    let dp = unsafe { pac::Peripherals::steal() };
+    let mut rcu = dp.RCU.configure().freeze();
+    let gpioc = dp.GPIOC.split(&mut rcu);
+    let mut red = RED::new(gpioc.pc13);
+    let mut delay = McycleDelay::new(&rcu.clocks);
+
+    let mut pattern = Pattern::sos().tick_period(10);
+    loop {
+        pattern.tick(&mut red);
+        delay.delay_ms(10u32);
+    }
+}
@@ -0,0 +1,328 @@
+//! Real time clock
+//!
+//! [`Clock`] runs the RTC from the 32.768 kHz crystal of the board and counts
+//! the seconds since 1970-01-01 00:00:00, [`DateTime`] converts them to a
+//! calendar date. The RTC is part of the backup domain: it keeps counting
+//! across resets, and while the board is unpowered if a battery is connected
+//! to VBAT.
+//!
+//! ```
+//! let mut pmu = dp.PMU;
+//! let mut backup_domain = dp.BKP.configure(&mut rcu, &mut pmu);
+//! let mut clock = rtc::configure(dp.RTC, &mut backup_domain, &rcu).unwrap();
+//! if !clock.is_set() {
+//!     clock.set(&"2024-05-01 12:00:00".parse().unwrap());
+//! }
+//! sprintln!("{}", clock.now());
+//!
+//! // Files created on the card get the date of the clock
+//! let sdcard = sdcard::configure_with_time_source(
+//!     dp.SPI1,
+//!     sdcard_pins,
+//!     SdCardFreq::Safe,
+//!     clock.time_source(),
+//!     &mut rcu,
+//! );
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+use gd32vf103xx_hal::backup_domain::BackupDomain;
+use gd32vf103xx_hal::pac::{BKP, RCU, RTC};
+use gd32vf103xx_hal::rcu::Rcu;
+use gd32vf103xx_hal::rtc::Rtc;
+use riscv::register::mcycle;
+
+/// Written to backup data register 0 when the clock is set
+const SET_MARKER: u16 = 0x4c4e;
+
+/// Longest time the crystal may take to start
+const CRYSTAL_STARTUP_MS: u32 = 2000;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// Days from 0000-03-01 to 1970-01-01
+const DAYS_TO_EPOCH: u32 = 719_468;
+
+/// Days in 400 years
+const DAYS_PER_ERA: u32 = 146_097;
+
+/// RTC start error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The 32.768 kHz crystal did not start, it may be missing or damaged
+    NoCrystal,
+}
+
+/// Starts the RTC from the 32.768 kHz crystal
+///
+/// Waits up to 2 s for the crystal to be stable, `rcu` gives the clock
+/// speed. The counter is left untouched, so the time survives resets.
+pub fn configure(rtc: RTC, backup_domain: &mut BackupDomain, rcu: &Rcu) -> Result<Clock, Error> {
+    start_crystal(rcu)?;
+
+    // The counter can only be read once it has been synchronised again
+    // after the reset
+    rtc.ctl.modify(|_, w| w.rsynf().clear_bit());
+
+    Ok(Clock {
+        rtc: Rtc::rtc(rtc, backup_domain),
+    })
+}
+
+/// Turns the crystal on and waits for it to be stable, so that the HAL,
+/// which waits without a timeout, finds it running
+fn start_crystal(rcu: &Rcu) -> Result<(), Error> {
+    let regs = unsafe { &*RCU::ptr() };
+    regs.bdctl
+        .modify(|_, w| w.lxtalen().set_bit().lxtalbps().clear_bit());
+
+    let timeout = u64::from(rcu.clocks.sysclk().0 / 1_000) * u64::from(CRYSTAL_STARTUP_MS);
+    let start = mcycle::read64();
+    while regs.bdctl.read().lxtalstb().bit_is_clear() {
+        if mcycle::read64().wrapping_sub(start) > timeout {
+            return Err(Error::NoCrystal);
+        }
+    }
+    Ok(())
+}
+
+/// Date and time kept by the RTC
+///
+/// Backup data register 0 records whether the clock has been set, it
+/// should not be used by the application.
+pub struct Clock {
+    rtc: Rtc,
+}
+
+impl Clock {
+    /// Returns `true` if the clock has been set since the backup domain was
+    /// last powered up
+    pub fn is_set(&self) -> bool {
+        let bkp = unsafe { &*BKP::ptr() };
+        bkp.data0.read().data().bits() == SET_MARKER
+    }
+
+    /// Returns the current date and time
+    pub fn now(&self) -> DateTime {
+        DateTime::from_timestamp(self.timestamp())
+    }
+
+    /// Returns the number of seconds since 1970-01-01 00:00:00
+    pub fn timestamp(&self) -> u32 {
+        read_counter().unwrap_or(0)
+    }
+
+    /// Sets the date and time
+    pub fn set(&mut self, datetime: &DateTime) {
+        self.set_timestamp(datetime.timestamp());
+    }
+
+    /// Sets the number of seconds since 1970-01-01 00:00:00
+    pub fn set_timestamp(&mut self, seconds: u32) {
+        self.rtc.set_time(seconds);
+        let bkp = unsafe { &*BKP::ptr() };
+        bkp.data0.write(|w| unsafe { w.data().bits(SET_MARKER) });
+    }
+
+    /// Returns a time source for
+    /// [`sdcard::configure_with_time_source`](crate::sdcard::configure_with_time_source)
+    pub fn time_source(&self) -> RtcTimeSource {
+        RtcTimeSource {}
+    }
+
+    /// Gives access to the RTC for alarms and second interrupts
+    pub fn rtc(&mut self) -> &mut Rtc {
+        &mut self.rtc
+    }
+}
+
+/// Reads the RTC counter, `None` if the RTC is not running from the crystal
+fn read_counter() -> Option<u32> {
+    let bdctl = unsafe { &*RCU::ptr() }.bdctl.read();
+    if bdctl.rtcen().bit_is_clear()
+        || bdctl.lxtalstb().bit_is_clear()
+        || bdctl.rtcsrc().bits() != 0b01
+    {
+        return None;
+    }
+
+    let rtc = unsafe { &*RTC::ptr() };
+    while rtc.ctl.read().rsynf().bit_is_clear() {}
+
+    // The halves are read separately, read again if the low one wrapped
+    loop {
+        let high = rtc.cnth.read().bits();
+        let low = rtc.cntl.read().bits();
+        if rtc.cnth.read().bits() == high {
+            return Some(high << 16 | low);
+        }
+    }
+}
+
+/// Source of the file timestamps of the SD card, reading the RTC
+///
+/// Files are dated 1970-01-01 while the RTC is not running, e.g. if
+/// [`configure`] failed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RtcTimeSource {}
+
+#[cfg(feature = "sdcard")]
+impl embedded_sdmmc::TimeSource for RtcTimeSource {
+    fn get_timestamp(&self) -> embedded_sdmmc::Timestamp {
+        let now = DateTime::from_timestamp(read_counter().unwrap_or(0));
+        embedded_sdmmc::Timestamp {
+            year_since_1970: (now.year - 1970) as u8,
+            zero_indexed_month: now.month - 1,
+            zero_indexed_day: now.day - 1,
+            hours: now.hours,
+            minutes: now.minutes,
+            seconds: now.seconds,
+        }
+    }
+}
+
+/// Calendar date and time, without time zone
+///
+/// Only the dates from 1970 to 2105 fit in the RTC counter. The text form is
+/// `YYYY-MM-DD HH:MM:SS`, a `T` between the date and time is also accepted
+/// when parsing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: u16,
+    /// Month, from 1
+    pub month: u8,
+    /// Day of the month, from 1
+    pub day: u8,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+impl DateTime {
+    /// Returns `None` if a field is out of range
+    pub fn new(year: u16, month: u8, day: u8, hours: u8, minutes: u8, seconds: u8) -> Option<Self> {
+        let valid = (1970..=2105).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hours < 24
+            && minutes < 60
+            && seconds < 60;
+        if !valid {
+            return None;
+        }
+        Some(DateTime {
+            year,
+            month,
+            day,
+            hours,
+            minutes,
+            seconds,
+        })
+    }
+
+    /// Converts a number of seconds since 1970-01-01 00:00:00
+    pub fn from_timestamp(seconds: u32) -> Self {
+        let time = seconds % SECONDS_PER_DAY;
+
+        // Years starting in March, so that the leap day ends them
+        let days = seconds / SECONDS_PER_DAY + DAYS_TO_EPOCH;
+        let era = days / DAYS_PER_ERA;
+        let day_of_era = days % DAYS_PER_ERA;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = era * 400 + year_of_era + (month <= 2) as u32;
+
+        DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hours: (time / 3600) as u8,
+            minutes: (time / 60 % 60) as u8,
+            seconds: (time % 60) as u8,
+        }
+    }
+
+    /// Returns the number of seconds since 1970-01-01 00:00:00
+    pub fn timestamp(&self) -> u32 {
+        let month = u32::from(self.month);
+        let year = u32::from(self.year).wrapping_sub((month <= 2) as u32);
+        let era = year / 400;
+        let year_of_era = year % 400;
+        let shifted_month = (month + 9) % 12;
+        let day_of_year = ((153 * shifted_month + 2) / 5 + u32::from(self.day)).wrapping_sub(1);
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = (era * DAYS_PER_ERA + day_of_era).wrapping_sub(DAYS_TO_EPOCH);
+
+        let time =
+            u32::from(self.hours) * 3600 + u32::from(self.minutes) * 60 + u32::from(self.seconds);
+        days.wrapping_mul(SECONDS_PER_DAY).wrapping_add(time)
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hours, self.minutes, self.seconds
+        )
+    }
+}
+
+/// Text that is not a valid date and time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseDateTimeError;
+
+impl FromStr for DateTime {
+    type Err = ParseDateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().as_bytes();
+        if s.len() != 19
+            || s[4] != b'-'
+            || s[7] != b'-'
+            || !matches!(s[10], b' ' | b'T')
+            || s[13] != b':'
+            || s[16] != b':'
+        {
+            return Err(ParseDateTimeError);
+        }
+        let number = |start: usize, len: usize| {
+            s[start..start + len]
+                .iter()
+                .try_fold(0u16, |value, digit| match digit {
+                    b'0'..=b'9' => Ok(value * 10 + u16::from(digit - b'0')),
+                    _ => Err(ParseDateTimeError),
+                })
+        };
+
+        DateTime::new(
+            number(0, 4)?,
+            number(5, 2)? as u8,
+            number(8, 2)? as u8,
+            number(11, 2)? as u8,
+            number(14, 2)? as u8,
+            number(17, 2)? as u8,
+        )
+        .ok_or(ParseDateTimeError)
+    }
+}
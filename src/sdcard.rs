@@ -39,7 +39,9 @@ type Spi1 = Spi<SPI1, SPI1Pins>;
 pub type SdCardSpi = SdMmcSpi<Spi1, CsPin>;
 
 /// A type based on embedded_sdmmc::Controller.
-pub type SdCard = Controller<SdCardSpi, FakeTimeSource>;
+///
+/// `T` gives the timestamps of the files, see [`configure_with_time_source`].
+pub type SdCard<T = FakeTimeSource> = Controller<SdCardSpi, T>;
 
 pub struct SdCardPins {
     pub miso: MisoPin,
@@ -68,7 +70,24 @@ impl From<SdCardFreq> for Hertz {
 }
 
 /// Constructs SD Card driver from the required components.
+///
+/// Files are dated 1970-01-01, see [`configure_with_time_source`] to date
+/// them from the RTC.
 pub fn configure(spi: SPI1, pins: SdCardPins, freq: SdCardFreq, rcu: &mut Rcu) -> SdCard {
+    configure_with_time_source(spi, pins, freq, FakeTimeSource {}, rcu)
+}
+
+/// Constructs SD Card driver, stamping the files created or modified on the
+/// card with the time given by `time_source`
+///
+/// [`Clock::time_source`](crate::rtc::Clock::time_source) reads the RTC.
+pub fn configure_with_time_source<T: TimeSource>(
+    spi: SPI1,
+    pins: SdCardPins,
+    freq: SdCardFreq,
+    time_source: T,
+    rcu: &mut Rcu,
+) -> SdCard<T> {
     let spi1 = Spi::spi1(
         spi,
         (pins.sck, pins.miso, pins.mosi),
@@ -81,9 +100,8 @@ pub fn configure(spi: SPI1, pins: SdCardPins, freq: SdCardFreq, rcu: &mut Rcu) -
     cs.set_high().unwrap();
 
     let sdmmcspi = SdMmcSpi::new(spi1, cs);
-    let ctime_source = FakeTimeSource {};
 
-    Controller::new(sdmmcspi, ctime_source)
+    Controller::new(sdmmcspi, time_source)
 }
 
 /// A fake time source that always returns a date of zero.
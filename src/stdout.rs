@@ -1,41 +1,294 @@
 //! Stdout based on the UART hooked up to the debug connector
+//!
+//! By default every write blocks until the last byte has been handed to the
+//! UART. [`enable_buffering`] switches stdout to a ring buffer that is
+//! drained in the background, either by the USART0 transmit interrupt or by
+//! DMA0 channel 3.
+//!
+//! The receive half of the UART is kept as well: [`try_read_byte`] and
+//! [`read_line`] read from it directly, or from an interrupt-fed ring buffer
+//! once [`enable_rx_buffering`] has been called.
+//!
+//! With the `log` feature, [`Logger`] routes the `log` crate macros to
+//! stdout. With the `defmt` feature, defmt frames are sent over the same
+//! UART, see [`defmt_logger`].
 
 use core::fmt::{self, Write};
 use nb::block;
 use riscv::interrupt;
 use gd32vf103xx_hal::{
-    serial::{Serial, Tx},
+    serial::{Serial, Rx, Tx},
     gpio::{Active, gpioa::{PA10, PA9}},
     time::Bps,
     rcu::Rcu,
     afio::Afio,
+    dma::{dma0::C3, Direction, Event},
     pac::USART0,
     prelude::*
 };
 use gd32vf103xx_hal::serial::{Config, Parity, StopBits};
 
+#[cfg(feature = "defmt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "defmt")))]
+pub mod defmt_logger;
+#[cfg(feature = "log")]
+mod logger;
+
+#[cfg(feature = "log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log")))]
+pub use logger::Logger;
 
 static mut STDOUT: Option<SerialWrapper> = None;
 
+/// Bytes handed to DMA in one go
+const DMA_CHUNK_SIZE: usize = 32;
 
-struct SerialWrapper(Tx<USART0>);
+/// Data currently transferred by DMA, copied out of the ring buffer so the
+/// ring buffer can be overwritten while the transfer is running
+static mut DMA_CHUNK: [u8; DMA_CHUNK_SIZE] = [0; DMA_CHUNK_SIZE];
 
-impl fmt::Write for SerialWrapper {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        for byte in s.as_bytes() {
-            if *byte == '\n' as u8 {
-                let res = block!(self.0.write('\r' as u8));
 
-                if res.is_err() {
-                    return Err(::core::fmt::Error);
+/// What to do when the stdout buffer is full
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the data that does not fit
+    Drop,
+    /// Wait until there is room in the buffer
+    ///
+    /// Interrupts are enabled while waiting, unless the caller disabled
+    /// them, so the drain interrupt keeps running.
+    Block,
+    /// Discard the oldest data in the buffer
+    Overwrite,
+}
+
+/// How the stdout buffer is drained
+pub enum Drain {
+    /// USART0 transmit interrupt, [`on_usart0_interrupt`] must be called
+    /// from the `USART0` interrupt handler
+    Interrupt,
+    /// DMA0 channel 3, [`on_dma_interrupt`] must be called from the
+    /// `DMA0_CHANNEL3` interrupt handler
+    ///
+    /// A transfer starts when a print is complete or a chunk of data is
+    /// ready, not on every byte.
+    Dma(C3),
+}
+
+struct Buffer {
+    data: &'static mut [u8],
+    head: usize,
+    tail: usize,
+    len: usize,
+    policy: OverflowPolicy,
+    dma: Option<C3>,
+    dma_busy: bool,
+}
+
+impl Buffer {
+    fn is_full(&self) -> bool {
+        self.len == self.data.len()
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.data[self.head] = byte;
+        self.head = (self.head + 1) % self.data.len();
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.data[self.tail];
+        self.tail = (self.tail + 1) % self.data.len();
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// Returns `true` once all the data has been handed to the UART
+    fn is_drained(&self) -> bool {
+        self.len == 0 && !self.dma_busy
+    }
+}
+
+struct SerialWrapper {
+    tx: Tx<USART0>,
+    rx: Rx<USART0>,
+    buffer: Option<Buffer>,
+    rx_buffer: Option<Buffer>,
+    /// Set after a `\r` so that a following `\n` doesn't end another line
+    skip_lf: bool,
+}
+
+impl SerialWrapper {
+    /// Queues `byte`, or sends it right away when unbuffered
+    ///
+    /// Returns `false` if the buffer is full with [`OverflowPolicy::Block`],
+    /// the byte has to be written again once there is room.
+    fn write_byte(&mut self, byte: u8) -> bool {
+        let buffer = match self.buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => {
+                let _ = block!(self.tx.write(byte));
+                return true;
+            }
+        };
+
+        if buffer.is_full() {
+            match buffer.policy {
+                OverflowPolicy::Drop => return true,
+                OverflowPolicy::Overwrite => {
+                    buffer.pop();
+                }
+                OverflowPolicy::Block => {
+                    // Also drains the buffer when interrupts are disabled
+                    self.service();
+                    return false;
+                }
+            }
+        }
+
+        buffer.push(byte);
+        // DMA is only started once a chunk is ready, the writers kick it
+        // when they are done so that a whole message goes out at once
+        if buffer.dma.is_none() || buffer.len >= DMA_CHUNK_SIZE || buffer.is_full() {
+            self.kick();
+        }
+        true
+    }
+
+    /// Makes sure the buffer is being drained
+    fn kick(&mut self) {
+        let buffer = match self.buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        if buffer.dma.is_some() {
+            Self::start_dma(buffer);
+        } else if buffer.len != 0 {
+            self.tx.listen();
+        }
+    }
+
+    /// Moves data from the buffer to the UART if the hardware is ready
+    fn service(&mut self) {
+        let buffer = match self.buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        if let Some(dma) = buffer.dma.as_mut() {
+            if buffer.dma_busy && dma.intf().ftfif3().bit_is_set() {
+                dma.stop();
+                buffer.dma_busy = false;
+            }
+            Self::start_dma(buffer);
+        } else {
+            while buffer.len != 0 {
+                let byte = buffer.data[buffer.tail];
+                if self.tx.write(byte).is_err() {
+                    return;
+                }
+                buffer.pop();
+            }
+            self.tx.unlisten();
+        }
+    }
+
+    /// Moves received data to the receive buffer
+    fn receive(&mut self) {
+        let buffer = match self.rx_buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        loop {
+            match self.rx.read() {
+                Ok(byte) => {
+                    if !buffer.is_full() {
+                        buffer.push(byte);
+                    }
                 }
+                Err(nb::Error::WouldBlock) => break,
+                // The error flags are cleared by the read
+                Err(nb::Error::Other(_)) => {}
             }
+        }
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        match self.rx_buffer.as_mut() {
+            Some(buffer) => buffer.pop(),
+            None => loop {
+                match self.rx.read() {
+                    Ok(byte) => break Some(byte),
+                    Err(nb::Error::WouldBlock) => break None,
+                    Err(nb::Error::Other(_)) => {}
+                }
+            },
+        }
+    }
 
-            let res = block!(self.0.write(*byte));
+    /// Starts the next DMA transfer if DMA is idle and there is data to send
+    fn start_dma(buffer: &mut Buffer) {
+        if buffer.dma.is_none() || buffer.dma_busy || buffer.len == 0 {
+            return;
+        }
+
+        let chunk = unsafe { &mut DMA_CHUNK };
+        let mut len = 0;
+        while len < chunk.len() {
+            match buffer.pop() {
+                Some(byte) => chunk[len] = byte,
+                None => break,
+            }
+            len += 1;
+        }
 
-            if res.is_err() {
-                return Err(::core::fmt::Error);
+        if let Some(dma) = buffer.dma.as_mut() {
+            unsafe {
+                dma.set_memory_address(chunk.as_ptr() as u32, true);
             }
+            dma.set_transfer_length(len);
+            dma.start();
+            buffer.dma_busy = true;
+        }
+    }
+}
+
+/// Writes to stdout with one critical section per byte, so that interrupts
+/// can run while a write waits for room in the buffer
+struct Writer;
+
+impl Writer {
+    fn write_byte(&mut self, byte: u8) {
+        while !interrupt::free(|| unsafe {
+            match STDOUT.as_mut() {
+                Some(stdout) => stdout.write_byte(byte),
+                None => true,
+            }
+        }) {}
+    }
+
+    /// Starts draining what has been written
+    fn kick(&mut self) {
+        interrupt::free(|| unsafe {
+            if let Some(stdout) = STDOUT.as_mut() {
+                stdout.kick();
+            }
+        })
+    }
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.as_bytes() {
+            if *byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+
+            self.write_byte(*byte);
         }
         Ok(())
     }
@@ -55,33 +308,221 @@ pub fn configure<X, Y>(
         stopbits: StopBits::STOP1
     };
     let serial = Serial::new(uart, (tx, rx), config, afio, rcu);
-    let (tx, _) = serial.split();
+    let (tx, rx) = serial.split();
 
     interrupt::free(|| {
         unsafe {
-            STDOUT.replace(SerialWrapper(tx));
+            STDOUT.replace(SerialWrapper {
+                tx,
+                rx,
+                buffer: None,
+                rx_buffer: None,
+                skip_lf: false,
+            });
         }
     })
 }
 
-/// Writes string to stdout
-pub fn write_str(s: &str) {
+/// Returns `true` if stdout has been configured
+pub fn is_configured() -> bool {
+    interrupt::free(|| unsafe { STDOUT.is_some() })
+}
+
+/// Switches the already configured stdout to buffered output
+///
+/// Output is queued in `buffer` and sent in the background. The interrupt
+/// selected by `drain` has to be enabled in the ECLIC and its handler has
+/// to call the matching function of this module:
+///
+/// ```
+/// static mut BUFFER: [u8; 256] = [0; 256];
+///
+/// let dma0 = dp.DMA0.split(&mut rcu);
+/// stdout::enable_buffering(unsafe { &mut BUFFER }, Drain::Dma(dma0.4), OverflowPolicy::Drop);
+/// ECLIC::setup(Interrupt::DMA0_CHANNEL3, TriggerType::Level, Level::L1, Priority::P1);
+/// unsafe { ECLIC::unmask(Interrupt::DMA0_CHANNEL3) };
+///
+/// #[no_mangle]
+/// fn DMA0_CHANNEL3() {
+///     stdout::on_dma_interrupt();
+/// }
+/// ```
+///
+/// Does nothing if stdout has not been configured.
+pub fn enable_buffering(buffer: &'static mut [u8], drain: Drain, policy: OverflowPolicy) {
+    flush();
+
+    let dma = match drain {
+        Drain::Interrupt => None,
+        Drain::Dma(mut dma) => {
+            let usart = unsafe { &*USART0::ptr() };
+            unsafe {
+                dma.set_peripheral_address(&usart.data as *const _ as u32, false);
+            }
+            dma.set_direction(Direction::MemoryToPeripherial);
+            dma.listen(Event::TransferComplete);
+            Some(dma)
+        }
+    };
+
+    interrupt::free(move || unsafe {
+        if let Some(stdout) = STDOUT.as_mut() {
+            stdout.buffer = Some(Buffer {
+                data: buffer,
+                head: 0,
+                tail: 0,
+                len: 0,
+                policy,
+                dma,
+                dma_busy: false,
+            });
+        }
+    })
+}
+
+/// Feeds received data into a ring buffer
+///
+/// The USART0 receive interrupt is enabled, [`on_usart0_interrupt`] must be
+/// called from the `USART0` interrupt handler and the interrupt has to be
+/// enabled in the ECLIC. Data received while `buffer` is full is dropped.
+///
+/// Does nothing if stdout has not been configured.
+pub fn enable_rx_buffering(buffer: &'static mut [u8]) {
+    interrupt::free(move || unsafe {
+        if let Some(stdout) = STDOUT.as_mut() {
+            stdout.rx_buffer = Some(Buffer {
+                data: buffer,
+                head: 0,
+                tail: 0,
+                len: 0,
+                policy: OverflowPolicy::Drop,
+                dma: None,
+                dma_busy: false,
+            });
+            stdout.rx.listen();
+        }
+    })
+}
+
+/// Drains the stdout buffer and fills the receive buffer, must be called
+/// from the `USART0` interrupt handler
+pub fn on_usart0_interrupt() {
     interrupt::free(|| unsafe {
         if let Some(stdout) = STDOUT.as_mut() {
-            let _ = stdout.write_str(s);
+            stdout.receive();
+            stdout.service();
         }
     })
 }
 
-/// Writes formatted string to stdout
-pub fn write_fmt(args: fmt::Arguments) {
+/// Drains the stdout buffer, must be called from the `DMA0_CHANNEL3` interrupt handler
+pub fn on_dma_interrupt() {
     interrupt::free(|| unsafe {
         if let Some(stdout) = STDOUT.as_mut() {
-            let _ = stdout.write_fmt(args);
+            stdout.service();
         }
     })
 }
 
+/// Returns the next received byte, if any
+pub fn try_read_byte() -> Option<u8> {
+    interrupt::free(|| unsafe {
+        STDOUT.as_mut().and_then(SerialWrapper::read_byte)
+    })
+}
+
+/// Reads a line into `buf`, returning the number of bytes read
+///
+/// Blocks until a `\r` or `\n` is received. The input is echoed back,
+/// backspace and delete remove the last character. Only printable ASCII
+/// is stored, other characters and characters that don't fit into `buf`
+/// are ignored. The line terminator is not stored.
+///
+/// Returns 0 immediately if stdout has not been configured.
+pub fn read_line(buf: &mut [u8]) -> usize {
+    if !is_configured() {
+        return 0;
+    }
+
+    let mut len = 0;
+    loop {
+        let byte = match try_read_byte() {
+            Some(byte) => byte,
+            None => continue,
+        };
+
+        let skip_lf = interrupt::free(|| unsafe {
+            match STDOUT.as_mut() {
+                Some(stdout) => core::mem::replace(&mut stdout.skip_lf, byte == b'\r'),
+                None => false,
+            }
+        });
+
+        match byte {
+            b'\n' if skip_lf => {}
+            b'\r' | b'\n' => {
+                write_str("\n");
+                return len;
+            }
+            0x08 | 0x7f if len > 0 => {
+                len -= 1;
+                write_str("\x08 \x08");
+            }
+            0x20..=0x7e if len < buf.len() => {
+                buf[len] = byte;
+                len += 1;
+                // Printable ASCII is valid UTF-8
+                write_str(core::str::from_utf8(&[byte]).unwrap_or(""));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Blocks until everything written to stdout has been transmitted
+pub fn flush() {
+    loop {
+        let done = interrupt::free(|| unsafe {
+            match STDOUT.as_mut() {
+                Some(stdout) => {
+                    stdout.service();
+                    stdout.buffer.as_ref().map_or(true, Buffer::is_drained)
+                }
+                None => true,
+            }
+        });
+        if done {
+            break;
+        }
+    }
+
+    interrupt::free(|| unsafe {
+        if let Some(stdout) = STDOUT.as_mut() {
+            let _ = block!(stdout.tx.flush());
+        }
+    })
+}
+
+/// Writes string to stdout
+pub fn write_str(s: &str) {
+    let _ = Writer.write_str(s);
+    Writer.kick();
+}
+
+/// Writes bytes to stdout as they are, without turning `\n` into `\r\n`
+pub fn write_bytes(bytes: &[u8]) {
+    for byte in bytes {
+        Writer.write_byte(*byte);
+    }
+    Writer.kick();
+}
+
+/// Writes formatted string to stdout
+pub fn write_fmt(args: fmt::Arguments) {
+    let _ = Writer.write_fmt(args);
+    Writer.kick();
+}
+
 /// Macro for printing to the serial standard output
 #[macro_export]
 macro_rules! sprint {
@@ -0,0 +1,87 @@
+//! `defmt` transport
+//!
+//! Encoded defmt frames are written to the stdout UART, so [`super::configure`]
+//! has to be called before anything is logged. Frames are rzCOBS encoded and
+//! separated by zero bytes, plain text written with `sprint!` on the same
+//! port will confuse the decoder.
+//!
+//! The firmware has to be linked with the defmt linker script, e.g. by
+//! adding `"-C", "link-arg=-Tdefmt.x"` to the rustflags in
+//! `.cargo/config.toml`. On the host, the frames are decoded with
+//! `defmt-print` (`cargo install defmt-print`) and the ELF file of the
+//! firmware:
+//!
+//! ```sh
+//! stty -F /dev/ttyUSB0 115200 raw -echo
+//! defmt-print -e target/riscv32imac-unknown-none-elf/release/examples/defmt < /dev/ttyUSB0
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use riscv::interrupt;
+use riscv::register::mstatus;
+
+use super::STDOUT;
+
+#[defmt::global_logger]
+struct Logger;
+
+/// Set while a frame is being written
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Whether interrupts have to be re-enabled on release
+static mut RESTORE_INTERRUPTS: bool = false;
+
+static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        let interrupts_enabled = mstatus::read().mie();
+        unsafe {
+            interrupt::disable();
+        }
+
+        if TAKEN.load(Ordering::Relaxed) {
+            panic!("defmt logger taken reentrantly");
+        }
+        TAKEN.store(true, Ordering::Relaxed);
+
+        unsafe {
+            RESTORE_INTERRUPTS = interrupts_enabled;
+            ENCODER.start_frame(write_bytes);
+        }
+    }
+
+    unsafe fn flush() {
+        super::flush();
+    }
+
+    unsafe fn release() {
+        ENCODER.end_frame(write_bytes);
+        if let Some(stdout) = STDOUT.as_mut() {
+            stdout.kick();
+        }
+        TAKEN.store(false, Ordering::Relaxed);
+
+        if RESTORE_INTERRUPTS {
+            interrupt::enable();
+        }
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        ENCODER.write(bytes, write_bytes);
+    }
+}
+
+/// Writes encoded bytes, called with interrupts disabled
+///
+/// Frames must not be interleaved, so a blocking buffer is drained here by
+/// polling instead of leaving the critical section.
+fn write_bytes(bytes: &[u8]) {
+    unsafe {
+        if let Some(stdout) = STDOUT.as_mut() {
+            for byte in bytes {
+                while !stdout.write_byte(*byte) {}
+            }
+        }
+    }
+}
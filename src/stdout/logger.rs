@@ -0,0 +1,105 @@
+//! `log` backend
+
+use core::fmt::Write;
+use gd32vf103xx_hal::time::Hertz;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use riscv::register::mcycle;
+
+use super::Writer;
+
+/// Logger printing to stdout
+///
+/// ```
+/// static LOGGER: Logger = Logger::new(LevelFilter::Info)
+///     .with_filters(&[("my_driver::spi", LevelFilter::Trace)])
+///     .with_timestamp(Hertz(108_000_000));
+///
+/// LOGGER.init().unwrap();
+/// log::info!("started");
+/// ```
+pub struct Logger {
+    level: LevelFilter,
+    filters: &'static [(&'static str, LevelFilter)],
+    timestamp_clock: Option<Hertz>,
+}
+
+impl Logger {
+    /// Creates a logger printing messages up to `level`
+    pub const fn new(level: LevelFilter) -> Self {
+        Logger {
+            level,
+            filters: &[],
+            timestamp_clock: None,
+        }
+    }
+
+    /// Overrides the level for modules
+    ///
+    /// Each entry applies to a module path and all its submodules. When
+    /// several entries match, the one with the longest path wins.
+    pub const fn with_filters(mut self, filters: &'static [(&'static str, LevelFilter)]) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Prefixes messages with the time since reset, computed from the
+    /// mcycle counter running at `sysclk`
+    pub const fn with_timestamp(mut self, sysclk: Hertz) -> Self {
+        self.timestamp_clock = Some(sysclk);
+        self
+    }
+
+    /// Installs the logger
+    pub fn init(&'static self) -> Result<(), SetLoggerError> {
+        log::set_logger(self)?;
+
+        let max_level = self.filters
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, core::cmp::max);
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    /// Returns the level that applies to `target`
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let mut best: Option<(&str, LevelFilter)> = None;
+        for (path, level) in self.filters {
+            let matches = target.starts_with(path)
+                && (target.len() == path.len() || target[path.len()..].starts_with("::"));
+            if matches && best.map_or(true, |(p, _)| path.len() > p.len()) {
+                best = Some((path, *level));
+            }
+        }
+        best.map_or(self.level, |(_, level)| level)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if let Some(clock) = self.timestamp_clock {
+            let ms = mcycle::read64() / u64::from(clock.0 / 1_000).max(1);
+            let _ = write!(Writer, "[{:>5}.{:03}] ", ms / 1_000, ms % 1_000);
+        }
+        let _ = writeln!(
+            Writer,
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        Writer.kick();
+    }
+
+    fn flush(&self) {
+        super::flush();
+    }
+}